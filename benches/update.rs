@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+const DT: f64 = 1. / 60.;
+
+/// A grid full of falling material: sand on the left, water on the right, and a wood floor
+fn make_full_grid(size: i32, parallel: bool) -> Grid {
+    let mut grid = Grid::with_seed(size, size, 42);
+    grid.set_parallel(parallel);
+    for y in 0..size {
+        for x in 0..size {
+            let material = if y >= size - size / 10 {
+                WOOD_ID
+            } else if (x + y) % 3 == 0 {
+                EMPTY_ID
+            } else if x < size / 2 {
+                SAND_ID
+            } else {
+                WATER_ID
+            };
+            grid.spawn((x, y), material);
+        }
+    }
+    grid
+}
+
+fn bench_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+    group.sample_size(20);
+
+    for size in [160, 1000] {
+        for (name, parallel) in [("serial", false), ("parallel", true)] {
+            group.bench_with_input(BenchmarkId::new(name, format!("{size}x{size}")), &size, |b, &size| {
+                let mut grid = make_full_grid(size, parallel);
+                b.iter(|| grid.update(DT));
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_update);
+criterion_main!(benches);
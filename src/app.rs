@@ -1,53 +1,107 @@
 extern crate sdl2;
 
-use crate::sandsim::particle::*;
-use crate::ui::Ui;
-use crate::sandsim::brush_settings::*;
-use crate::sandsim::grid::{Grid, PIXEL_SIZE};
+use sandgamebase::sandsim::particle::*;
+use crate::ui::{Ui, PIXEL_SIZE};
+use sandgamebase::sandsim::brush_settings::*;
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::clock::SimulationClock;
+use sandgamebase::sandsim::material::MaterialRegistry;
+use sandgamebase::sandsim::level::{FitMode, Palette};
+use sandgamebase::sandsim::recorder::{Recorder, RecorderSettings};
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Material file loaded from the working directory, instead of the built-in materials, if it exists
+const MATERIALS_PATH: &str = "materials.toml";
+/// Snapshot written and read by the quick-save and quick-load keys
+const QUICKSAVE_PATH: &str = "quicksave.sgs";
+/// Level image loaded by the level key, and its optional palette (the material colors are used otherwise)
+const LEVEL_PATH: &str = "level.png";
+const PALETTE_PATH: &str = "palette.toml";
 
 pub struct App {
     // width: i32,
     // height: i32,
     ui: Ui,
     
-    paused: bool,
+    clock: SimulationClock,
     grid: Grid,
     brush_settings_map: HashMap<ParticleId, BrushSettings>,
+    brush_keys: HashMap<Keycode, ParticleId>,
     selected_brush: ParticleId,
+    recorder: Option<Recorder>,
 }
 
 impl App {
-    pub fn new() -> App {
+    /// Creates the app. The simulation is seeded with `seed` if given, or a random seed otherwise.
+    pub fn new(seed: Option<u64>) -> App {
         let width = 800;
         let height = 800;
         let title = "Sandgame";
         let fps_target = 90; //TODO Create our own FPS manager because the one from SDL is not working
 
         let ui = Ui::new(width, height, title, fps_target); 
+        let materials = if Path::new(MATERIALS_PATH).exists() {
+            println!("Loading materials from {}", MATERIALS_PATH);
+            MaterialRegistry::load(MATERIALS_PATH).unwrap_or_else(|error| {
+                println!("{}", error);
+                println!("Using the built-in materials instead");
+                MaterialRegistry::builtin()
+            })
+        } else {
+            MaterialRegistry::builtin()
+        };
+        let brush_settings_map = make_brush_settings_map(&materials);
+        let brush_keys = materials.iter()
+            .filter_map(|material| {
+                let key = material.brush.as_ref()?.key.as_ref()?;
+                let Some(keycode) = Keycode::from_name(key) else {
+                    println!("Unknown key \"{}\" for the {} brush, the brush has no key", key, material.name);
+                    return None;
+                };
+                Some((keycode, material.id))
+            })
+            .collect();
+
+        let seed = seed.unwrap_or_else(rand::random);
+        #[allow(unused_mut)]
+        let mut grid = Grid::with_materials(width / PIXEL_SIZE, height / PIXEL_SIZE, seed, Arc::new(materials));
+        println!("Seed: {}", grid.seed());
+        #[cfg(feature = "parallel")]
+        grid.set_parallel(true);
+
         App {
             // width,
             // height,
             ui,
-            paused: false,
+            clock: SimulationClock::default(),
 
-            grid: Grid::new(width, height),
-            brush_settings_map: make_default_brush_settings_map(),
+            grid,
+            brush_settings_map,
+            brush_keys,
             selected_brush: SAND_ID,
+            recorder: None,
         }
     }
 
     pub fn run(&mut self) {
+        let mut last_frame = Instant::now();
         while !self.ui.requested_app_closing(){
             self.ui.clear();
             for event in self.ui.get_events() {
                 self.handle_event(event);
             }
 
-            self.update();
+            let now = Instant::now();
+            let elapsed = now.duration_since(last_frame).as_secs_f64();
+            last_frame = now;
+
+            self.update(elapsed);
             self.draw();
 
             self.ui.finish_frame();
@@ -55,7 +109,7 @@ impl App {
         }
     }
     
-    pub fn update(&mut self) {
+    pub fn update(&mut self, elapsed: f64) {
         // Inputs
         let mouse_state = self.ui.event_pump.mouse_state();
         if mouse_state.left() {
@@ -65,9 +119,9 @@ impl App {
             let grid_x = x / PIXEL_SIZE;
             let grid_y = y / PIXEL_SIZE;
 
-            self.grid.set_circle(
-                (grid_x, grid_y),
-                self.brush_settings_map.get(&self.selected_brush).unwrap());
+            if let Some(brush_settings) = self.brush_settings_map.get(&self.selected_brush) {
+                self.grid.set_circle((grid_x, grid_y), brush_settings);
+            }
         }
 
         if mouse_state.right() {
@@ -77,32 +131,117 @@ impl App {
             let grid_x = x / PIXEL_SIZE;
             let grid_y = y / PIXEL_SIZE;
 
-            self.grid.set_circle((grid_x, grid_y), self.brush_settings_map.get(&EMPTY_ID).unwrap());
+            if let Some(eraser) = self.brush_settings_map.get(&EMPTY_ID) {
+                self.grid.set_circle((grid_x, grid_y), eraser);
+            }
         }
 
-        // Logic
-        let dt = if self.paused {
-            0.
-        } else {
-            1. / 60. //TODO Tmp, will need to update to actual dt
-        };
-        self.grid.update(dt);
+        // Logic, at a fixed timestep whatever the frame rate
+        let ticks = self.clock.advance(elapsed);
+        for _ in 0..ticks {
+            self.grid.update(self.clock.dt());
+            self.capture();
+        }
     }
 
     pub fn draw(&mut self) {
-        self.grid.draw(&mut self.ui.canvas);
+        self.ui.draw_grid(&mut self.grid);
     }
 
     pub fn handle_event(&mut self, event: Event) {
         match event {
-            Event::KeyDown { keycode: Some(Keycode::Num1), .. } => { self.selected_brush = SAND_ID; println!("Selected sand");  },
-            Event::KeyDown { keycode: Some(Keycode::Num2), .. } => { self.selected_brush = WOOD_ID; println!("Selected wood");  },
-            Event::KeyDown { keycode: Some(Keycode::Num3), .. } => { self.selected_brush = SMOKE_ID; println!("Selected smoke"); },
-            Event::KeyDown { keycode: Some(Keycode::Num4), .. } => { self.selected_brush = FIRE_ID; println!("Selected fire"); },
-            Event::KeyDown { keycode: Some(Keycode::Num5), .. } => { self.selected_brush = WATER_ID; println!("Selected water"); },
-            Event::KeyDown { keycode: Some(Keycode::Num0), .. } => { self.selected_brush = EMPTY_ID; println!("Selected eraser"); },
-            Event::KeyDown { keycode: Some(Keycode::Space), repeat: false, .. } => { self.paused = !self.paused; println!("Pause: {}", self.paused); },
+            Event::KeyDown { keycode: Some(keycode), .. } if self.brush_keys.contains_key(&keycode) => {
+                self.selected_brush = self.brush_keys[&keycode];
+                println!("Selected {}", self.grid.materials().get(self.selected_brush).unwrap().name);
+            },
+            Event::KeyDown { keycode: Some(Keycode::Space), repeat: false, .. } => { self.clock.toggle_pause(); println!("Pause: {}", self.clock.is_paused()); },
+            Event::KeyDown { keycode: Some(Keycode::Period), .. } => { self.clock.step(); },
+            Event::KeyDown { keycode: Some(Keycode::Equals | Keycode::KpPlus), .. } => { self.clock.speed_up(); println!("Time scale: {}x", self.clock.time_scale()); },
+            Event::KeyDown { keycode: Some(Keycode::Minus | Keycode::KpMinus), .. } => { self.clock.slow_down(); println!("Time scale: {}x", self.clock.time_scale()); },
+            Event::KeyDown { keycode: Some(Keycode::F5), repeat: false, .. } => self.quick_save(),
+            Event::KeyDown { keycode: Some(Keycode::F9), repeat: false, .. } => self.quick_load(),
+            Event::KeyDown { keycode: Some(Keycode::F7), repeat: false, .. } => self.load_level(),
+            Event::KeyDown { keycode: Some(Keycode::F12), repeat: false, .. } => self.screenshot(),
+            Event::KeyDown { keycode: Some(Keycode::F10), repeat: false, .. } => self.toggle_recording(),
             _ => (),
         }
     }
+
+    fn quick_save(&mut self) {
+        match self.grid.save(QUICKSAVE_PATH) {
+            Ok(()) => println!("Saved to {}", QUICKSAVE_PATH),
+            Err(error) => println!("{}", error),
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.recorder.is_some() {
+            self.stop_recording();
+            return;
+        }
+
+        let path = format!("recording-{}.gif", self.grid.tick());
+        match Recorder::start(&path, self.grid.width, self.grid.height, RecorderSettings::default()) {
+            Ok(recorder) => {
+                self.recorder = Some(recorder);
+                println!("Recording to {}", path);
+            },
+            Err(error) => println!("{}", error),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            match recorder.finish() {
+                Ok(frames) => println!("Recording stopped, {} frames", frames),
+                Err(error) => println!("{}", error),
+            }
+        }
+    }
+
+    /// Records the current tick, if a recording is in progress
+    fn capture(&mut self) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if let Err(error) = recorder.capture(&self.grid) {
+            println!("{}", error);
+            self.recorder = None;
+        } else if recorder.is_finished() {
+            self.stop_recording();
+        }
+    }
+
+    /// Saves the grid as it looks in the window, named after the current tick
+    fn screenshot(&self) {
+        let path = format!("screenshot-{}.png", self.grid.tick());
+        match self.grid.save_screenshot(&path, PIXEL_SIZE as u32) {
+            Ok(()) => println!("Saved {}", path),
+            Err(error) => println!("{}", error),
+        }
+    }
+
+    fn load_level(&mut self) {
+        let palette = if Path::new(PALETTE_PATH).exists() {
+            Palette::load(PALETTE_PATH, self.grid.materials())
+        } else {
+            Ok(Palette::from_materials(self.grid.materials()))
+        };
+        match palette.and_then(|palette| self.grid.load_level(LEVEL_PATH, &palette, FitMode::Resize)) {
+            Ok(()) => println!("Loaded {}", LEVEL_PATH),
+            Err(error) => println!("{}", error),
+        }
+    }
+
+    fn quick_load(&mut self) {
+        match Grid::load(QUICKSAVE_PATH, self.grid.materials().clone()) {
+            Ok(grid) => {
+                self.grid = grid;
+                #[cfg(feature = "parallel")]
+                self.grid.set_parallel(true);
+                println!("Loaded {}", QUICKSAVE_PATH);
+            },
+            Err(error) => println!("{}", error),
+        }
+    }
 }
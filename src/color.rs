@@ -1,20 +1,61 @@
-use sdl2::pixels::Color;
 use colors_transform::{Rgb, Hsl, Color as ColorTransform};
 use rand::Rng;
 
-pub fn vary_color(color: Color, variance: i8) -> Color {
+use serde::Deserialize;
+
+use crate::sandsim::grid::SimRng;
+
+/// Renderer-agnostic RGBA color used by the simulation.
+/// Deserializes from `[r, g, b]` or `[r, g, b, a]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "Vec<u8>")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const YELLOW: Color = Color::rgb(255, 255, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+impl TryFrom<Vec<u8>> for Color {
+    type Error = String;
+
+    fn try_from(components: Vec<u8>) -> Result<Self, Self::Error> {
+        match components[..] {
+            [r, g, b] => Ok(Color::rgb(r, g, b)),
+            [r, g, b, a] => Ok(Color::rgba(r, g, b, a)),
+            _ => Err(format!("a color needs 3 or 4 components, got {}", components.len())),
+        }
+    }
+}
+
+/// Largest variance accepted by `vary_color`, which changes the saturation by up to twice as much
+pub const MAX_COLOR_VARIANCE: i8 = 63;
+
+pub fn vary_color(color: Color, variance: i8, rng: &mut SimRng) -> Color {
     let rgb = Rgb::from(color.r as f32, color.g as f32, color.b as f32);
     let hsl = rgb.to_hsl();
 
-    let mut rng = rand::thread_rng();
-    
     let hue = hsl.get_hue().floor();
     let saturation = hsl.get_saturation() + (rng.gen_range(-2 * variance..=0) as f32);
     let lightness = hsl.get_lightness() + (rng.gen_range(-variance..=variance) as f32);
 
-    let new_hsl = Hsl::from(hue, saturation.min(100.).max(0.), lightness.min(100.).max(0.));
+    let new_hsl = Hsl::from(hue, saturation.clamp(0., 100.), lightness.clamp(0., 100.));
     let new_rgb = new_hsl.to_rgb();
-    Color::RGB(new_rgb.get_red() as u8, new_rgb.get_green() as u8, new_rgb.get_blue() as u8)
+    Color::rgb(new_rgb.get_red() as u8, new_rgb.get_green() as u8, new_rgb.get_blue() as u8)
 }
 
 pub fn darken_color(color: Color, lightness: f32) -> Color {
@@ -25,12 +66,11 @@ pub fn darken_color(color: Color, lightness: f32) -> Color {
     let saturation = hsl.get_saturation();
     let lightness = lightness.min(hsl.get_lightness());
 
-    let new_hsl = Hsl::from(hue, saturation.min(100.).max(0.), lightness.min(100.).max(0.));
+    let new_hsl = Hsl::from(hue, saturation.clamp(0., 100.), lightness.clamp(0., 100.));
     let new_rgb = new_hsl.to_rgb();
-    Color::RGB(new_rgb.get_red() as u8, new_rgb.get_green() as u8, new_rgb.get_blue() as u8)
+    Color::rgb(new_rgb.get_red() as u8, new_rgb.get_green() as u8, new_rgb.get_blue() as u8)
 }
 
-#[allow(dead_code)]
 pub fn color_interpolation(a: Color, b: Color, t: f64) -> Color {
     Color {
         r: (a.r as f64 + (b.r as f64 - a.r as f64) * t).floor() as u8,
@@ -38,4 +78,4 @@ pub fn color_interpolation(a: Color, b: Color, t: f64) -> Color {
         b: (a.b as f64 + (b.b as f64 - a.b as f64) * t).floor() as u8,
         a: (a.a as f64 + (b.a as f64 - a.a as f64) * t).floor() as u8,
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,7 @@
+//! Headless falling-sand simulation.
+//!
+//! Everything in this crate is independent of any renderer: the SDL front end
+//! in the `sandgamebase` binary is only one consumer of the grid.
+
+pub mod sandsim;
+pub mod color;
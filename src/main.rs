@@ -1,11 +1,11 @@
 mod ui;
 mod app;
-mod sandsim;
-mod color;
 
 use app::App;
 
 pub fn main() {
-    let mut app = App::new();
+    // An optional seed can be passed as the first argument to replay a run
+    let seed = std::env::args().nth(1).map(|arg| arg.parse().expect("The seed must be an unsigned integer"));
+    let mut app = App::new(seed);
     app.run();
-}
\ No newline at end of file
+}
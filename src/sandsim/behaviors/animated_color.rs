@@ -1,4 +1,4 @@
-use sdl2::pixels::Color;
+use crate::color::Color;
 
 use crate::sandsim::behaviors::*;
 
@@ -15,7 +15,30 @@ impl Behavior for AnimatedColor {
         ANIMATED_COLOR_ID
     }
 
-    fn update(&mut self, _state: &mut ParticleState, dt: f64, _grid: &mut Vec<Vec<ParticleId>>, _behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
+    fn is_idle(&self) -> bool {
+        false // Changes color over time
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.colors.len() as u16);
+        for color in &self.colors {
+            writer.write_color(*color);
+        }
+        writer.write_f64(self.frequency);
+        writer.write_f64(self.elapsed_time);
+        writer.write_u16(self.last_index as u16);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        let len = reader.read_u16()?;
+        self.colors = (0..len).map(|_| reader.read_color()).collect::<Result<_, _>>()?;
+        self.frequency = reader.read_f64()?;
+        self.elapsed_time = reader.read_f64()?;
+        self.last_index = reader.read_u16()? as usize;
+        Ok(())
+    }
+
+    fn update(&mut self, _state: &mut ParticleState, dt: f64, _neighbourhood: &mut Neighbourhood, _rng: &mut SimRng) -> Vec<ParticleAction> {
         self.elapsed_time += dt;
         let mut index = (self.elapsed_time * self.frequency).floor() as usize;
         if index >= self.colors.len() {
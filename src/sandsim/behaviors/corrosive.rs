@@ -0,0 +1,76 @@
+use rand::Rng;
+use crate::sandsim::behaviors::*;
+use crate::sandsim::material::Phase;
+
+/// `Corrosive` is a struct that implements the `Behavior` trait.
+/// This behavior makes the particle dissolve the particles next to it, unless they resist thanks to the
+/// `corrosion_resistance` of their material. Each dissolved particle costs one unit of `strength`, and the particle
+/// disappears once it has none left. Gases and other corrosive particles are never dissolved.
+pub struct Corrosive {
+    rate: f64, // The number of times per second the particle attacks one of its neighbours
+    gas: Option<ParticleId>, // Released in place of the dissolved particles
+    gas_probability: f64,
+
+    strength: f64, // The number of particles the particle can still dissolve
+    has_target: bool, // Whether a neighbour can be dissolved
+}
+
+impl Behavior for Corrosive {
+    fn get_id(&self) -> BehaviorId {
+        CORROSIVE_ID
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.has_target
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f64(self.strength);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        self.strength = reader.read_f64()?;
+        Ok(())
+    }
+
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let (x, y) = state.position;
+        let targets: Vec<Position> = [(1, 0), (0, 1), (-1, 0), (0, -1)].into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|position| Self::can_dissolve(*position, neighbourhood))
+            .collect();
+        self.has_target = !targets.is_empty();
+        if !self.has_target || rng.gen::<f64>() >= self.rate * dt {
+            return vec![];
+        }
+
+        let position = targets[rng.gen_range(0..targets.len())];
+        if rng.gen::<f64>() < neighbourhood.material(position).corrosion_resistance {
+            return vec![];
+        }
+
+        let mut actions = vec![ParticleAction::KillParticle { position }];
+        if let Some(gas) = self.gas.filter(|_| rng.gen::<f64>() < self.gas_probability) {
+            actions.push(ParticleAction::SpawnParticle { material: gas, position });
+        }
+        self.strength -= 1.;
+        if self.strength <= 0. {
+            actions.push(ParticleAction::KillParticle { position: state.position });
+        }
+        actions
+    }
+}
+
+impl Corrosive {
+    pub fn boxed(rate: f64, strength: f64, gas: Option<ParticleId>, gas_probability: f64) -> Box<dyn Behavior> {
+        Box::new(Self { rate, gas, gas_probability, strength, has_target: true })
+    }
+
+    fn can_dissolve(position: Position, neighbourhood: &Neighbourhood) -> bool {
+        if !neighbourhood.in_bounds(position) || neighbourhood.particle_id(position) == EMPTY_ID || neighbourhood.has_behavior(position, CORROSIVE_ID) {
+            return false;
+        }
+        let material = neighbourhood.material(position);
+        material.phase != Phase::Gas && material.corrosion_resistance < 1.
+    }
+}
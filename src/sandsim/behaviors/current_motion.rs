@@ -1,3 +1,4 @@
+use rand::Rng;
 use crate::sandsim::behaviors::*;
 
 /// `CurrentMotion` is a struct that implements the `Behavior` trait.
@@ -11,21 +12,19 @@ impl Behavior for CurrentMotion {
         CURRENT_MOTION_ID
     }
 
-    fn update(&mut self, state: &mut ParticleState, dt: f64, grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
-        let width = grid[0].len() as i32;
-
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
         // Pick a random side
-        let dx = if rand::random::<f64>() < 0.5 { -1 } else { 1 };
+        let dx = if rng.gen::<f64>() < 0.5 { -1 } else { 1 };
         let x = state.position.0;
         let nx = x + dx;
 
         // If both particle are the same time, and given the swap_probability, swap them
-        if nx >= 0 && nx < width && rand::random::<f64>() < self.swap_probability_per_sec * dt {
+        if neighbourhood.in_bounds((nx, state.position.1)) && rng.gen::<f64>() < self.swap_probability_per_sec * dt {
             // Expect them to have the same behavior ID and particle ID
-            if grid[state.position.1 as usize][nx as usize] != grid[state.position.1 as usize][x as usize] {
+            if neighbourhood.particle_id((nx, state.position.1)) != neighbourhood.particle_id((x, state.position.1)) {
                 return vec![];
             }
-            if behaviors_grid[state.position.1 as usize][nx as usize] != behaviors_grid[state.position.1 as usize][x as usize] {
+            if neighbourhood.behaviors_ids((nx, state.position.1)) != neighbourhood.behaviors_ids((x, state.position.1)) {
                 return vec![];
             }
 
@@ -1,3 +1,4 @@
+use rand::Rng;
 use crate::sandsim::behaviors::*;
 
 pub struct DieWhenCrushed {
@@ -9,15 +10,15 @@ impl Behavior for DieWhenCrushed {
         DIE_WHEN_CRUSHED_ID
     }
 
-    fn update(&mut self, state: &mut ParticleState, _dt: f64, grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
+    fn update(&mut self, state: &mut ParticleState, _dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
         // If the material on top of it is not AirLike, and the probability is met, kill the particle
         let above_x = state.position.0;
         let above_y = state.position.1 - 1;
 
-        if above_y >= 0 
-        && grid[above_y as usize][above_x as usize] != state.particle_id
-        && !has_behavior((above_x, above_y), behaviors_grid, AIR_LIKE_ID)
-        && rand::random::<f64>() < self.crushing_probability {
+        if neighbourhood.in_bounds((above_x, above_y))
+        && neighbourhood.particle_id((above_x, above_y)) != state.particle_id
+        && !neighbourhood.has_behavior((above_x, above_y), AIR_LIKE_ID)
+        && rng.gen::<f64>() < self.crushing_probability {
             return vec![
                 ParticleAction::KillParticle { position: state.position },
             ];
@@ -0,0 +1,36 @@
+use rand::Rng;
+use crate::sandsim::behaviors::*;
+
+/// `Emitter` is a struct that implements the `Behavior` trait.
+/// This behavior makes the particle release particles of another material, e.g. smoke, into the empty cell above it.
+pub struct Emitter {
+    material: ParticleId, // The material released
+    rate: f64, // The average number of particles released per second
+
+    can_emit: bool, // Whether the cell above is empty
+}
+
+impl Behavior for Emitter {
+    fn get_id(&self) -> BehaviorId {
+        EMITTER_ID
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.can_emit
+    }
+
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let above = (state.position.0, state.position.1 - 1);
+        self.can_emit = neighbourhood.in_bounds(above) && neighbourhood.particle_id(above) == EMPTY_ID;
+        if self.can_emit && rng.gen::<f64>() < self.rate * dt {
+            return vec![ParticleAction::SpawnParticle { material: self.material, position: above }];
+        }
+        vec![]
+    }
+}
+
+impl Emitter {
+    pub fn boxed(material: ParticleId, rate: f64) -> Box<dyn Behavior> {
+        Box::new(Self { material, rate, can_emit: true })
+    }
+}
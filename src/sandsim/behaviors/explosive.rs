@@ -0,0 +1,84 @@
+use rand::Rng;
+use crate::sandsim::behaviors::*;
+
+// Probabilities for each destroyed cell to be replaced by fire, or else by smoke
+const FIRE_PROBABILITY: f64 = 0.35;
+const SMOKE_PROBABILITY: f64 = 0.35;
+
+/// `Explosive` is a struct that implements the `Behavior` trait.
+/// This behavior makes the particle explode next to an igniter, or when it gets too hot: the cells within `radius` are
+/// destroyed and replaced by fire and smoke, and the falling particles up to twice as far are thrown away.
+/// Explosives caught in the blast explode too, during the same tick.
+pub struct Explosive {
+    radius: i32, // Cells within this distance are destroyed
+    strength: f64, // Solids withstand the blast if their strength is at least this high, or if they have none
+    impulse: f64, // Velocity given to the particles thrown by the blast, decreasing with the distance (cells/s)
+    ignition_temperature: Option<f64>, // If set, the particle also explodes at this temperature
+    fire: ParticleId, // The materials filling the blast
+    smoke: ParticleId,
+}
+
+impl Behavior for Explosive {
+    fn get_id(&self) -> BehaviorId {
+        EXPLOSIVE_ID
+    }
+
+    fn update(&mut self, state: &mut ParticleState, _dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let (x, y) = state.position;
+        let too_hot = self.ignition_temperature.is_some_and(|temperature| state.temperature >= temperature);
+        let next_to_igniter = (-1..=1).any(|dx| (-1..=1).any(|dy| neighbourhood.has_behavior((x + dx, y + dy), IGNITER_ID)));
+        if too_hot || next_to_igniter {
+            return self.blast(state, neighbourhood, rng);
+        }
+        vec![]
+    }
+
+    fn detonate(&mut self, state: &ParticleState, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        self.blast(state, neighbourhood, rng)
+    }
+}
+
+impl Explosive {
+    pub fn boxed(radius: i32, strength: f64, impulse: f64, ignition_temperature: Option<f64>, fire: ParticleId, smoke: ParticleId) -> Box<dyn Behavior> {
+        Box::new(Self { radius, strength, impulse, ignition_temperature, fire, smoke })
+    }
+
+    fn blast(&self, state: &ParticleState, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let (x, y) = state.position;
+        let reach = 2 * self.radius;
+        let mut actions = vec![];
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let position = (x + dx, y + dy);
+                let distance_squared = dx * dx + dy * dy;
+                if distance_squared > reach * reach || !neighbourhood.in_bounds(position) {
+                    continue;
+                }
+
+                if distance_squared <= self.radius * self.radius {
+                    if position != state.position && !self.destroys(position, neighbourhood) {
+                        continue;
+                    }
+                    actions.push(ParticleAction::BlastParticle { position });
+                    let roll = rng.gen::<f64>();
+                    if roll < FIRE_PROBABILITY {
+                        actions.push(ParticleAction::SpawnParticle { material: self.fire, position });
+                    } else if roll < FIRE_PROBABILITY + SMOKE_PROBABILITY {
+                        actions.push(ParticleAction::SpawnParticle { material: self.smoke, position });
+                    }
+                } else if neighbourhood.has_behavior(position, MOVE_DOWN_ID) {
+                    // Outward, from full speed at the edge of the radius to nothing at twice the radius
+                    let distance = (distance_squared as f64).sqrt();
+                    let speed = self.impulse * (reach as f64 - distance) / self.radius as f64;
+                    actions.push(ParticleAction::ApplyImpulse { position, velocity: (speed * dx as f64 / distance, speed * dy as f64 / distance) });
+                }
+            }
+        }
+        actions
+    }
+
+    fn destroys(&self, position: Position, neighbourhood: &Neighbourhood) -> bool {
+        let material = neighbourhood.material(position);
+        !material.phase.is_solid() || material.strength.is_some_and(|strength| strength < self.strength)
+    }
+}
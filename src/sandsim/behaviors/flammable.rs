@@ -1,8 +1,11 @@
+use rand::Rng;
 use crate::sandsim::behaviors::*;
 
 pub struct Flammable {
     ignition_rate: f64, // The rate at which "current_ignition_probability" increases for each FIRE_ID in the given radius (Unit: prob/second)
     ignition_radius: i32, // The radius in which to check for FIRE_ID (Actually, checks on a square of side 2*ignition_radius + 1) 
+    burns_into: ParticleId, // The material replacing the particle when it ignites
+    ignition_temperature: Option<f64>, // If set, the particle ignites at this temperature instead of around igniters
     
     current_ignition_probability: f64, // The current probability of igniting
     num_cell_in_radius: f64, // The number of cells in the given radius
@@ -13,7 +16,28 @@ impl Behavior for Flammable {
         FLAMMABLE_ID
     }
 
-    fn update(&mut self, state: &mut ParticleState, dt: f64, _grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
+    fn is_idle(&self) -> bool {
+        self.current_ignition_probability <= 0.
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f64(self.current_ignition_probability);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        self.current_ignition_probability = reader.read_f64()?;
+        Ok(())
+    }
+
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        // Heat is brought by the temperature field, the particle is woken up when its temperature changes
+        if let Some(ignition_temperature) = self.ignition_temperature {
+            if state.temperature >= ignition_temperature {
+                return self.ignite(state);
+            }
+            return vec![];
+        }
+
         // Increase ignite probability based on the number of FIRE_ID in the given radius
         let mut any_fire_in_area = false;
         for i in -self.ignition_radius..=self.ignition_radius {
@@ -23,7 +47,7 @@ impl Behavior for Flammable {
                 }
                 // Check if there is a FIRE_ID in the given radius
                 // If there is, increase the current_ignition_probability
-                if has_behavior((state.position.0 + i, state.position.1 + j), behaviors_grid, IGNITER_ID) {
+                if neighbourhood.has_behavior((state.position.0 + i, state.position.1 + j), IGNITER_ID) {
                     self.current_ignition_probability += self.ignition_rate * dt / self.num_cell_in_radius;
                     any_fire_in_area = true;
                 }
@@ -37,11 +61,8 @@ impl Behavior for Flammable {
         }
 
         // Check if the current cell catches fire
-        if rand::random::<f64>() < self.current_ignition_probability {
-            return vec![
-                ParticleAction::KillParticle { position: state.position },
-                ParticleAction::SpawnParticle { callback: |pos| Particle::new_fire(pos), position: state.position },
-            ];
+        if rng.gen::<f64>() < self.current_ignition_probability {
+            return self.ignite(state);
         }
 
         vec![]
@@ -49,13 +70,35 @@ impl Behavior for Flammable {
 }
 
 impl Flammable {
-    pub fn boxed(ignition_rate: f64, ignition_radius: i32) -> Box<dyn Behavior> {
+    pub fn boxed(ignition_rate: f64, ignition_radius: i32, burns_into: ParticleId) -> Box<dyn Behavior> {
         Box::new(Self {
             ignition_radius,
+            burns_into,
             ignition_rate,
+            ignition_temperature: None,
 
             current_ignition_probability: 0.,
             num_cell_in_radius: ((2 * ignition_radius + 1) * (2 * ignition_radius + 1)) as f64,
         })
     }
+
+    /// A particle igniting once its temperature reaches `ignition_temperature`
+    pub fn boxed_with_temperature(ignition_temperature: f64, burns_into: ParticleId) -> Box<dyn Behavior> {
+        Box::new(Self {
+            ignition_radius: 0,
+            burns_into,
+            ignition_rate: 0.,
+            ignition_temperature: Some(ignition_temperature),
+
+            current_ignition_probability: 0.,
+            num_cell_in_radius: 1.,
+        })
+    }
+
+    fn ignite(&self, state: &ParticleState) -> Vec<ParticleAction> {
+        vec![
+            ParticleAction::KillParticle { position: state.position },
+            ParticleAction::SpawnParticle { material: self.burns_into, position: state.position },
+        ]
+    }
 }
\ No newline at end of file
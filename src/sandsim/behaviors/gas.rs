@@ -0,0 +1,68 @@
+use rand::Rng;
+use crate::sandsim::behaviors::*;
+use crate::sandsim::material::Phase;
+
+// Offset between °C and K, the pressure of a gas being proportional to its absolute temperature
+const ZERO_CELSIUS: f64 = 273.15;
+
+/// `Gas` is a struct that implements the `Behavior` trait.
+/// This behavior makes a particle wander randomly through the other gases around it, and burst through the solids
+/// holding it when its pressure gets higher than their `strength`.
+/// The pressure itself is spread by the grid, see `Grid::spread_pressure`.
+pub struct Gas {
+    diffusion_rate: f64, // The number of times per second the particle tries to swap places with a neighbouring gas
+
+    can_diffuse: bool, // Whether a neighbouring cell holds another gas
+}
+
+impl Behavior for Gas {
+    fn get_id(&self) -> BehaviorId {
+        GAS_ID
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.can_diffuse
+    }
+
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let (x, y) = state.position;
+
+        // Burst through the weakest solid around
+        let pressure = state.pressure * (state.temperature + ZERO_CELSIUS) / (AMBIENT_TEMPERATURE + ZERO_CELSIUS);
+        let weakest = [(1, 0), (0, 1), (-1, 0), (0, -1)].into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|position| neighbourhood.in_bounds(*position) && !neighbourhood.is_fluid(*position))
+            .filter_map(|position| neighbourhood.material(position).strength.map(|strength| (strength, position)))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((strength, position)) = weakest {
+            if pressure > strength {
+                return vec![ParticleAction::KillParticle { position }];
+            }
+        }
+
+        // Swap places with a random neighbour
+        let can_swap = |position: Position| {
+            neighbourhood.in_bounds(position)
+                && neighbourhood.material(position).phase == Phase::Gas
+                && neighbourhood.particle_id(position) != state.particle_id
+        };
+        self.can_diffuse = (-1..=1).any(|dx| (-1..=1).any(|dy| can_swap((x + dx, y + dy))));
+        if !self.can_diffuse || rng.gen::<f64>() >= self.diffusion_rate * dt {
+            return vec![];
+        }
+
+        let target = (x + rng.gen_range(-1..=1), y + rng.gen_range(-1..=1));
+        if !can_swap(target) {
+            return vec![];
+        }
+        neighbourhood.swap(state.position, target);
+        state.position = target;
+        vec![ParticleAction::SetPosition { position: target }]
+    }
+}
+
+impl Gas {
+    pub fn boxed(diffusion_rate: f64) -> Box<dyn Behavior> {
+        Box::new(Self { diffusion_rate, can_diffuse: true })
+    }
+}
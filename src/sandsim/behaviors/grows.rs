@@ -0,0 +1,132 @@
+use rand::Rng;
+use crate::sandsim::behaviors::*;
+
+/// `Grows` is a struct that implements the `Behavior` trait.
+/// This behavior turns the particle into the seed of a plant: it absorbs the `water` particles next to it, and each one
+/// lets the plant grow by a `stem` particle, up to `max_size`. The plant grows from its tips, straight up or sometimes
+/// diagonally (`sway_probability`), and a tip sometimes forks (`branch_probability`).
+/// The seed keeps track of the tips, the plant stops growing if the seed moves once it has sprouted.
+/// Tips only grow into empty cells. Blocked tips stop growing, while the seed and the tips out of its reach
+/// (see `Neighbourhood::in_bounds`) wait.
+pub struct Grows {
+    rate: f64, // The number of cells grown per second, while there is water
+    max_size: u32, // The number of stem particles the seed grows
+    sway_probability: f64, // The probability of growing diagonally
+    branch_probability: f64, // The probability of a tip forking each time it grows
+    stem: ParticleId,
+    water: ParticleId,
+
+    stored_water: u32, // Absorbed, not used yet
+    size: u32, // The number of stem particles grown
+    tips: Vec<Position>, // Relative to the seed
+    root: Position, // Position of the seed when it sprouted
+    growing: bool, // Whether the plant can still grow without changes around it
+}
+
+impl Behavior for Grows {
+    fn get_id(&self) -> BehaviorId {
+        GROWS_ID
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.growing
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u32(self.stored_water);
+        writer.write_u32(self.size);
+        writer.write_u16(self.tips.len() as u16);
+        for tip in &self.tips {
+            writer.write_i32(tip.0);
+            writer.write_i32(tip.1);
+        }
+        writer.write_i32(self.root.0);
+        writer.write_i32(self.root.1);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        self.stored_water = reader.read_u32()?;
+        self.size = reader.read_u32()?;
+        let len = reader.read_u16()?;
+        self.tips = (0..len).map(|_| Ok((reader.read_i32()?, reader.read_i32()?))).collect::<Result<_, SnapshotError>>()?;
+        self.root = (reader.read_i32()?, reader.read_i32()?);
+        Ok(())
+    }
+
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let (x, y) = state.position;
+        if self.size > 0 && state.position != self.root {
+            self.tips.clear(); // Torn off
+        }
+        // Burnt or dissolved tips
+        self.tips.retain(|&(dx, dy)| (dx, dy) == (0, 0) || !neighbourhood.in_bounds((x + dx, y + dy)) || neighbourhood.particle_id((x + dx, y + dy)) == self.stem);
+        if self.tips.is_empty() || self.size >= self.max_size {
+            self.growing = false;
+            return vec![];
+        }
+
+        let mut actions = vec![];
+        let water = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .find(|position| neighbourhood.in_bounds(*position) && neighbourhood.particle_id(*position) == self.water);
+        if let Some(position) = water.filter(|_| self.size + self.stored_water < self.max_size) {
+            actions.push(ParticleAction::KillParticle { position });
+            self.stored_water += 1;
+        }
+        self.growing = self.stored_water > 0 || water.is_some();
+
+        if self.stored_water > 0 && rng.gen::<f64>() < self.rate * dt {
+            actions.extend(self.grow(state.position, neighbourhood, rng));
+        }
+        actions
+    }
+}
+
+impl Grows {
+    pub fn boxed(rate: f64, max_size: u32, sway_probability: f64, branch_probability: f64, stem: ParticleId, water: ParticleId) -> Box<dyn Behavior> {
+        Box::new(Self {
+            rate,
+            max_size,
+            sway_probability,
+            branch_probability,
+            stem,
+            water,
+
+            stored_water: 0,
+            size: 0,
+            tips: vec![(0, 0)], // The seed itself
+            root: (0, 0),
+            growing: true,
+        })
+    }
+
+    /// Grows one of the tips by a stem particle, in the chosen direction or else in another free one
+    fn grow(&mut self, (x, y): Position, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        let index = rng.gen_range(0..self.tips.len());
+        let (tip_x, tip_y) = self.tips[index];
+        let side = if rng.gen::<f64>() < 0.5 { -1 } else { 1 };
+        let directions = if rng.gen::<f64>() < self.sway_probability { [side, 0, -side] } else { [0, side, -side] };
+
+        let free = directions.into_iter()
+            .map(|dx| (tip_x + dx, tip_y - 1))
+            .find(|&(dx, dy)| neighbourhood.in_bounds((x + dx, y + dy)) && neighbourhood.particle_id((x + dx, y + dy)) == EMPTY_ID);
+        let Some(new_tip) = free else {
+            // The seed waits for room above it, other tips stop unless they are out of reach
+            if (tip_x, tip_y) != (0, 0) && neighbourhood.in_bounds((x + tip_x, y + tip_y - 1)) {
+                self.tips.swap_remove(index);
+            }
+            return vec![];
+        };
+
+        if self.size == 0 {
+            self.root = (x, y);
+        }
+        self.size += 1;
+        self.stored_water -= 1;
+        if rng.gen::<f64>() < self.branch_probability {
+            self.tips.push(new_tip); // The old tip grows again later, in another direction
+        } else {
+            self.tips[index] = new_tip;
+        }
+        vec![ParticleAction::SpawnParticle { material: self.stem, position: (x + new_tip.0, y + new_tip.1) }]
+    }
+}
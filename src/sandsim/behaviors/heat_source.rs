@@ -0,0 +1,24 @@
+use crate::sandsim::behaviors::*;
+
+/// `HeatSource` is a struct that implements the `Behavior` trait.
+/// This behavior keeps the temperature of the particle at least at a given value, heating its surroundings.
+pub struct HeatSource {
+    temperature: f64,
+}
+
+impl Behavior for HeatSource {
+    fn get_id(&self) -> BehaviorId {
+        HEAT_SOURCE_ID
+    }
+
+    fn update(&mut self, state: &mut ParticleState, _dt: f64, _neighbourhood: &mut Neighbourhood, _rng: &mut SimRng) -> Vec<ParticleAction> {
+        state.temperature = state.temperature.max(self.temperature);
+        vec![]
+    }
+}
+
+impl HeatSource {
+    pub fn boxed(temperature: f64) -> Box<dyn Behavior> {
+        Box::new(Self { temperature })
+    }
+}
@@ -7,7 +7,7 @@ impl Behavior for Igniter {
         IGNITER_ID
     }
 
-    fn update(&mut self, _state: &mut ParticleState, _dt: f64, _grid: &mut Vec<Vec<ParticleId>>, _behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
+    fn update(&mut self, _state: &mut ParticleState, _dt: f64, _neighbourhood: &mut Neighbourhood, _rng: &mut SimRng) -> Vec<ParticleAction> {
         vec![]
     }
 }
@@ -6,13 +6,13 @@ pub struct LimitedLife {
     elapsed_time: f64,
 
     spawn_probability: f64,
-    spawn_callback: Option<fn(Position) -> Particle>,
+    spawn_material: Option<ParticleId>,
     spawn_distance: Position,
     lifetime: f64,
 }
 
 impl Behavior for LimitedLife {
-    fn update(&mut self, state: &mut ParticleState, dt: f64, grid: &mut Vec<Vec<ParticleId>>, _behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
         self.elapsed_time = self.lifetime.min(self.elapsed_time + dt);
 
         let t = (self.elapsed_time / self.lifetime) as f32; // t = 0 => start, t = 1 => end
@@ -23,12 +23,10 @@ impl Behavior for LimitedLife {
 
         if self.elapsed_time >= self.lifetime {
             actions.push(ParticleAction::KillParticle { position: state.position });
-            if self.spawn_callback.is_some() && rand::thread_rng().gen_range(0.0..=1.0) <= self.spawn_probability {
-                let width = grid[0].len();
-                let height = grid.len();
+            if let Some(material) = self.spawn_material.filter(|_| rng.gen_range(0.0..=1.0) <= self.spawn_probability) {
                 actions.push(ParticleAction::SpawnParticle {
-                    callback: self.spawn_callback.unwrap(),
-                    position: self.random_position(state.position, width as i32, height as i32),
+                    material,
+                    position: self.random_position(state.position, neighbourhood.width(), neighbourhood.height(), rng),
                 })
             }
         }
@@ -39,6 +37,21 @@ impl Behavior for LimitedLife {
     fn get_id(&self) -> BehaviorId {
         LIMITED_LIFE_ID
     }
+
+    fn is_idle(&self) -> bool {
+        false // Ages every tick
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f64(self.elapsed_time);
+        writer.write_f64(self.lifetime);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        self.elapsed_time = reader.read_f64()?;
+        self.lifetime = reader.read_f64()?;
+        Ok(())
+    }
 }
 
 impl LimitedLife {
@@ -48,14 +61,14 @@ impl LimitedLife {
             lifetime,
 
             spawn_probability: 0.,
-            spawn_callback: None,
+            spawn_material: None,
             spawn_distance: (0, 0),
         })
     }
 
     pub fn boxed_with_spawn(lifetime: f64,
                             spawn_probability: f64,
-                            spawn_callback: fn(Position) -> Particle,
+                            spawn_material: ParticleId,
                             spawn_distance: Position, )
         -> Box<dyn Behavior> {
         Box::new(Self {
@@ -63,14 +76,12 @@ impl LimitedLife {
 
             lifetime,
             spawn_probability,
-            spawn_callback: Some(spawn_callback),
+            spawn_material: Some(spawn_material),
             spawn_distance,
         })
     }
 
-    fn random_position(&self, central_position: Position, width: i32, height: i32) -> Position {
-        let mut rng = rand::thread_rng();
-
+    fn random_position(&self, central_position: Position, width: i32, height: i32, rng: &mut SimRng) -> Position {
         let dx = rng.gen_range(-self.spawn_distance.0..=self.spawn_distance.0);
         let dy = rng.gen_range(-self.spawn_distance.1..=self.spawn_distance.1);
 
@@ -0,0 +1,95 @@
+use rand::Rng;
+use crate::sandsim::behaviors::*;
+use crate::sandsim::chunks::CHUNK_SIZE;
+
+// How far a particle looks for a place to fall: the reach of a particle during a parallel update (see
+// `Grid::update_parallel`), so that the liquid flows the same way in both updates
+const MAX_DROP_DISTANCE: i32 = CHUNK_SIZE / 2;
+
+/// `Liquid` is a struct that implements the `Behavior` trait.
+/// This behavior makes a particle flow sideways when it cannot fall, towards the closest place where it can fall again,
+/// so that the surface of a liquid levels out. Falling itself is left to `MoveDown`.
+/// A particle next to cells out of reach (see `Neighbourhood::out_of_reach`) waits for a later update.
+pub struct Liquid {
+    dispersion_rate: i32, // The maximum number of cells the particle flows sideways in a tick
+    viscosity: f64, // The probability of not flowing during a tick, between 0 (water) and 1 (does not flow)
+
+    flowing: bool, // Whether the particle has somewhere to flow to
+}
+
+impl Behavior for Liquid {
+    fn get_id(&self) -> BehaviorId {
+        LIQUID_ID
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.flowing
+    }
+
+    fn update(&mut self, state: &mut ParticleState, _dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        self.flowing = false;
+
+        // Moved too far from its chunk during a parallel update
+        let (x, y) = state.position;
+        if [(x - MAX_DROP_DISTANCE, y), (x + MAX_DROP_DISTANCE, y), (x - MAX_DROP_DISTANCE, y + 1), (x + MAX_DROP_DISTANCE, y + 1)].iter()
+            .any(|position| neighbourhood.out_of_reach(*position)) {
+            self.flowing = true;
+            return vec![];
+        }
+
+        // Still falling, or surrounded
+        if (-1..=1).any(|dx| neighbourhood.can_displace((x, y), (x + dx, y + 1))) {
+            return vec![];
+        }
+        if !neighbourhood.can_displace((x, y), (x - 1, y)) && !neighbourhood.can_displace((x, y), (x + 1, y)) {
+            return vec![];
+        }
+
+        // Flow towards the closest drop, the first side checked being chosen randomly on ties
+        let first = if rng.gen::<f64>() < 0.5 { -1 } else { 1 };
+        let drops = [first, -first].map(|dx| Self::find_drop(state.position, dx, neighbourhood).map(|(distance, free)| (distance, free, dx)));
+        let Some((_, free, dx)) = drops.into_iter().flatten().min_by_key(|(distance, _, _)| *distance) else {
+            return vec![]; // Level with its surroundings
+        };
+
+        // Viscous liquids wait, and liquids queue behind the ones flowing in front of them, but they stay awake
+        self.flowing = true;
+        if free == 0 || rng.gen::<f64>() < self.viscosity {
+            return vec![];
+        }
+
+        // Swap particle and behaviors IDs
+        let target = (x + dx * free.min(self.dispersion_rate), y);
+        neighbourhood.swap(state.position, target);
+        state.position = target;
+        vec![ParticleAction::SetPosition { position: target }]
+    }
+}
+
+impl Liquid {
+    pub fn boxed(dispersion_rate: i32, viscosity: f64) -> Box<dyn Behavior> {
+        Box::new(Self { dispersion_rate, viscosity, flowing: false })
+    }
+
+    /// Looks in the direction `dx`, up to `MAX_DROP_DISTANCE` cells away, for the closest cell above a lighter liquid or
+    /// gas, going over other fluids but not solids. Returns its distance, and the number of cells the particle can flow
+    /// through before reaching a fluid it cannot displace.
+    fn find_drop((x, y): Position, dx: i32, neighbourhood: &Neighbourhood) -> Option<(i32, i32)> {
+        let density = neighbourhood.density((x, y));
+        let mut free = None;
+        for distance in 1..=MAX_DROP_DISTANCE {
+            let position = (x + dx * distance, y);
+            if !neighbourhood.is_fluid(position) {
+                return None;
+            }
+            if free.is_none() && !neighbourhood.can_displace((x, y), position) {
+                free = Some(distance - 1);
+            }
+            let below = (position.0, y + 1);
+            if neighbourhood.is_fluid(below) && neighbourhood.density(below) < density {
+                return Some((distance, free.unwrap_or(distance)));
+            }
+        }
+        None
+    }
+}
@@ -1,20 +1,51 @@
 use crate::sandsim::particle::*;
 use crate::sandsim::particle_action::ParticleAction;
-use crate::sandsim::grid::Position;
+use crate::sandsim::grid::{Position, SimRng};
+use crate::sandsim::neighbourhood::Neighbourhood;
+use crate::sandsim::snapshot::{SnapshotError, StateReader, StateWriter};
 
 pub type FloatPosition = (f64, f64);
-pub type BehaviorId = u16;
-
-pub const MOVE_DOWN_ID: BehaviorId = 1 << 1;
-pub const AIR_LIKE_ID: BehaviorId = 1 << 2;
-pub const LIMITED_LIFE_ID: BehaviorId = 1 << 3;
-pub const ANIMATED_COLOR_ID: BehaviorId = 1 << 4;
-pub const FLAMMABLE_ID: BehaviorId = 1 << 5;
-pub const DIE_WHEN_CRUSHED_ID: BehaviorId = 1 << 6;
-pub const IGNITER_ID: BehaviorId = 1 << 7;
-pub const SIDEWAY_MOTION_FALLBACK: BehaviorId = 1 << 8;
-pub const CURRENT_MOTION_ID: BehaviorId = 1 << 9;
 
+pub const MOVE_DOWN_ID: BehaviorId = BehaviorId::builtin(0);
+pub const AIR_LIKE_ID: BehaviorId = BehaviorId::builtin(1);
+pub const LIMITED_LIFE_ID: BehaviorId = BehaviorId::builtin(2);
+pub const ANIMATED_COLOR_ID: BehaviorId = BehaviorId::builtin(3);
+pub const FLAMMABLE_ID: BehaviorId = BehaviorId::builtin(4);
+pub const DIE_WHEN_CRUSHED_ID: BehaviorId = BehaviorId::builtin(5);
+pub const IGNITER_ID: BehaviorId = BehaviorId::builtin(6);
+pub const SIDEWAYS_MOTION_FALLBACK_ID: BehaviorId = BehaviorId::builtin(7);
+pub const CURRENT_MOTION_ID: BehaviorId = BehaviorId::builtin(8);
+pub const HEAT_SOURCE_ID: BehaviorId = BehaviorId::builtin(9);
+pub const PHASE_TRANSITION_ID: BehaviorId = BehaviorId::builtin(10);
+pub const LIQUID_ID: BehaviorId = BehaviorId::builtin(11);
+pub const GAS_ID: BehaviorId = BehaviorId::builtin(12);
+pub const EXPLOSIVE_ID: BehaviorId = BehaviorId::builtin(13);
+pub const CORROSIVE_ID: BehaviorId = BehaviorId::builtin(14);
+pub const EMITTER_ID: BehaviorId = BehaviorId::builtin(15);
+pub const GROWS_ID: BehaviorId = BehaviorId::builtin(16);
+
+// Reserved in the `BehaviorRegistry` before any other behavior, in this order
+const BUILTIN_BEHAVIORS: [(&str, BehaviorId); 17] = [
+    ("MoveDown", MOVE_DOWN_ID),
+    ("AirLike", AIR_LIKE_ID),
+    ("LimitedLife", LIMITED_LIFE_ID),
+    ("AnimatedColor", ANIMATED_COLOR_ID),
+    ("Flammable", FLAMMABLE_ID),
+    ("DieWhenCrushed", DIE_WHEN_CRUSHED_ID),
+    ("Igniter", IGNITER_ID),
+    ("SidewaysMotionFallback", SIDEWAYS_MOTION_FALLBACK_ID),
+    ("CurrentMotion", CURRENT_MOTION_ID),
+    ("HeatSource", HEAT_SOURCE_ID),
+    ("PhaseTransition", PHASE_TRANSITION_ID),
+    ("Liquid", LIQUID_ID),
+    ("Gas", GAS_ID),
+    ("Explosive", EXPLOSIVE_ID),
+    ("Corrosive", CORROSIVE_ID),
+    ("Emitter", EMITTER_ID),
+    ("Grows", GROWS_ID),
+];
+
+mod registry;
 mod move_down;
 mod air_like;
 mod limited_life;
@@ -24,7 +55,16 @@ mod die_when_crushed;
 mod igniter;
 mod sideways_motion_fallback;
 mod current_motion;
+mod heat_source;
+mod phase_transition;
+mod liquid;
+mod gas;
+mod explosive;
+mod corrosive;
+mod emitter;
+mod grows;
 
+pub use registry::{BehaviorId, BehaviorSet, BehaviorRegistry, MAX_BEHAVIORS};
 pub use move_down::MoveDown;
 pub use air_like::AirLike;
 pub use limited_life::LimitedLife;
@@ -34,13 +74,39 @@ pub use die_when_crushed::DieWhenCrushed;
 pub use igniter::Igniter;
 pub use sideways_motion_fallback::SidewaysMotionFallback;
 pub use current_motion::CurrentMotion;
+pub use heat_source::HeatSource;
+pub use phase_transition::PhaseTransition;
+pub use liquid::Liquid;
+pub use gas::Gas;
+pub use explosive::Explosive;
+pub use corrosive::Corrosive;
+pub use emitter::Emitter;
+pub use grows::Grows;
 
 
-pub trait Behavior {
-    fn update(&mut self, state: &mut ParticleState, dt: f64, grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction>;
+pub trait Behavior: Send {
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction>;
+    /// Id of the behavior, built-in or allocated by the `BehaviorRegistry`
     fn get_id(&self) -> BehaviorId;
-}
 
-fn has_behavior(position: Position, behaviors_grid: &Vec<Vec<BehaviorId>>, behavior_id: BehaviorId) -> bool {
-    behaviors_grid[position.1 as usize][position.0 as usize] & behavior_id != 0
+    /// Whether this behavior has nothing left to do until one of its neighbours changes.
+    /// Particles whose behaviors are all idle are not updated while their surroundings stay the same.
+    fn is_idle(&self) -> bool {
+        true
+    }
+
+    /// Called when the particle is destroyed by a blast, before it disappears. Explosives return their own blast,
+    /// which the grid carries out during the same tick.
+    fn detonate(&mut self, _state: &ParticleState, _neighbourhood: &Neighbourhood, _rng: &mut SimRng) -> Vec<ParticleAction> {
+        vec![]
+    }
+
+    /// Writes what changed since the behavior was created, or was picked randomly when it was, for snapshots.
+    /// Parameters coming from the material definition do not need to be saved.
+    fn save_state(&self, _writer: &mut StateWriter) {}
+
+    /// Restores the state written by `save_state`
+    fn load_state(&mut self, _reader: &mut StateReader) -> Result<(), SnapshotError> {
+        Ok(())
+    }
 }
@@ -1,12 +1,26 @@
+use rand::Rng;
 use crate::sandsim::behaviors::*;
 
+// Fraction of the speed of a particle landing on something that is turned into a sideways motion
+const IMPACT_TRANSFER: f64 = 0.15;
+// How fast a particle sliding on something stops (1/s)
+const FRICTION: f64 = 8.;
+// Sideways speeds below this are dropped, so that sliding particles come to rest (cells/s)
+const MIN_SPEED: f64 = 0.5;
+
+/// `MoveDown` is a struct that implements the `Behavior` trait.
+/// This behavior moves the particle according to its velocity (see `ParticleState::velocity`), which `acceleration`
+/// pulls down, or up when negative. The particle goes through the cells on its way one at a time, and stops at the first
+/// one it cannot enter: falling on something, it slides diagonally if it can, or else part of its speed becomes sideways.
+/// Cells out of reach during a parallel update (see `Neighbourhood::out_of_reach`) are not obstacles: the particle stops
+/// before them, keeping its velocity and the rest of its way for the next update.
 pub struct MoveDown {
     acceleration: f64,
-    max_velocity: f64,
-    velocity: f64,
+    max_velocity: f64, // On each axis
 
-    float_y: f64,
+    float_position: FloatPosition, // Position within the cell
     integer_position: Position,
+    moving: bool,
 }
 
 impl Behavior for MoveDown {
@@ -14,92 +28,181 @@ impl Behavior for MoveDown {
         MOVE_DOWN_ID
     }
 
-    fn update(&mut self, state: &mut ParticleState, dt: f64, grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
-        // Check if we have changed position between two frames
+    fn is_idle(&self) -> bool {
+        !self.moving
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f64(self.float_position.0);
+        writer.write_f64(self.float_position.1);
+        writer.write_i32(self.integer_position.0);
+        writer.write_i32(self.integer_position.1);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        self.float_position = (reader.read_f64()?, reader.read_f64()?);
+        self.integer_position = (reader.read_i32()?, reader.read_i32()?);
+        Ok(())
+    }
+
+    fn update(&mut self, state: &mut ParticleState, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        // Moved by another behavior, or by a particle sinking through this one
         if self.integer_position != state.position {
+            if self.integer_position.0 != state.position.0 {
+                self.float_position.0 = state.position.0 as f64;
+            }
+            if self.integer_position.1 != state.position.1 {
+                self.float_position.1 = state.position.1 as f64;
+            }
             self.integer_position = state.position;
-            // self.stop_motion();
-            // Should return there ?
         }
 
-        // Regular update
-        let sign = self.acceleration.signum();
-        self.velocity += self.acceleration * dt;
-        if self.velocity.abs() > self.max_velocity {
-            self.velocity = self.max_velocity * sign;
+        // Resting on something, wait for it to move
+        let resting = self.is_blocked(state.position, neighbourhood);
+        self.moving = state.velocity != (0., 0.) || !resting;
+        if !self.moving {
+            return vec![];
         }
-        self.float_y += self.velocity * dt;
 
-        // Check if we changed grid cell 
-        let mut new_position = self.to_integer_position();
-        if new_position == self.integer_position {
-            return vec![]; // We did not move (or moved within the same cell)
+        // Gravity, and friction when sliding on something
+        let (mut vx, mut vy) = state.velocity;
+        vy = (vy + self.acceleration * dt).clamp(-self.max_velocity, self.max_velocity);
+        if resting {
+            vx -= vx * (FRICTION * dt).min(1.);
         }
+        if vx.abs() < MIN_SPEED {
+            vx = 0.;
+        }
+        vx = vx.clamp(-self.max_velocity, self.max_velocity);
+        state.velocity = (vx, vy);
+        self.float_position = (self.float_position.0 + vx * dt, self.float_position.1 + vy * dt);
 
-        // We moved to a new cell, check if its in bounds
-        if new_position.1 < 0 || new_position.1 >= grid.len() as i32 { // Only check vertical axis because can only fall down
-            self.stop_motion();
-            return vec![]; // We are out of bounds
+        // Check if we changed grid cell
+        let target = self.to_integer_position();
+        if target == self.integer_position {
+            return vec![]; // We did not move (or moved within the same cell)
         }
 
-        // We are in bounds, find target empty cell
-        if let Some(dx) = self.find_empty_cell(state.position, new_position.1 - state.position.1, &grid, &behaviors_grid) {
-            new_position.0 += dx;
-            
-            // Swap particle IDs
-            grid[new_position.1 as usize][new_position.0 as usize] = grid[self.integer_position.1 as usize][self.integer_position.0 as usize];
-            grid[self.integer_position.1 as usize][self.integer_position.0 as usize] = EMPTY_ID;
-
-            // Swap behaviors IDs
-            let tmp = behaviors_grid[new_position.1 as usize][new_position.0 as usize];
-            behaviors_grid[new_position.1 as usize][new_position.0 as usize] = behaviors_grid[self.integer_position.1 as usize][self.integer_position.0 as usize];
-            behaviors_grid[self.integer_position.1 as usize][self.integer_position.0 as usize] = tmp;
-            
-            self.integer_position = new_position;
-
-            state.position = new_position;
-            vec![ParticleAction::SetPosition { position: new_position }]
-        } else {
-            // No available cell, stop
-            self.stop_motion();
-            vec![]
+        let start = self.integer_position;
+        let position = self.traverse(start, target, state, neighbourhood, rng);
+        self.moving = state.velocity != (0., 0.);
+        if position == start {
+            return vec![];
         }
+
+        // Swap particle and behaviors IDs
+        neighbourhood.swap(start, position);
+        self.integer_position = position;
+        state.position = position;
+        vec![ParticleAction::SetPosition { position }]
     }
 }
 
 impl MoveDown {
     pub fn boxed(position: Position, max_velocity: f64, acceleration: f64) -> Box<dyn Behavior> {
-        Box::new(Self { float_y: position.1 as f64, integer_position: position, max_velocity: max_velocity.abs(), acceleration, velocity: 0.})
+        Box::new(Self {
+            acceleration,
+            max_velocity: max_velocity.abs(),
+            float_position: (position.0 as f64, position.1 as f64),
+            integer_position: position,
+            moving: true,
+        })
     }
 
     fn to_integer_position(&self) -> Position {
-        (self.integer_position.0, self.float_y.round() as i32)
+        (self.float_position.0.round() as i32, self.float_position.1.round() as i32)
     }
 
-    fn stop_motion(&mut self) {
-        self.velocity = 0.;
-        self.float_y = self.integer_position.1 as f64;
+    /// Goes from `start` towards `target` one cell at a time, and returns where the particle stops.
+    /// The velocity loses what is stopped by the obstacle met on the way, if any.
+    fn traverse(&mut self, start: Position, target: Position, state: &mut ParticleState, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Position {
+        // The ids are only swapped at the end, the particle is still at `start` in the neighbourhood
+        let density = neighbourhood.density(start);
+        let (dx, dy) = (target.0 - start.0, target.1 - start.1);
+        let steps = dx.abs().max(dy.abs());
+        let mut position = start;
+        for i in 1..=steps {
+            let next = (
+                start.0 + (dx as f64 * i as f64 / steps as f64).round() as i32,
+                start.1 + (dy as f64 * i as f64 / steps as f64).round() as i32);
+            if neighbourhood.out_of_reach(next) {
+                return position;
+            }
+            if self.can_move_to(density, position, next, neighbourhood) {
+                position = next;
+                continue;
+            }
+
+            let Some(position) = self.hit(density, position, (next.0 - position.0, next.1 - position.1), state, neighbourhood, rng) else {
+                return position;
+            };
+            self.float_position = (position.0 as f64, position.1 as f64);
+            return position;
+        }
+        position
     }
 
-    fn find_empty_cell(&self, (x, y): Position, dy: i32, grid: &Vec<Vec<ParticleId>>, behaviors_grid: &Vec<Vec<BehaviorId>>) -> Option<i32> {
-        let self_air_like = has_behavior((x, y), behaviors_grid, AIR_LIKE_ID);
+    /// The step from `position` is blocked: returns where the particle goes instead, and updates its velocity.
+    /// Returns `None` if the cells to go to instead are out of reach.
+    fn hit(&self, density: f64, (x, y): Position, (step_x, step_y): Position, state: &mut ParticleState, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Option<Position> {
+        let (vx, vy) = state.velocity;
+
+        // Sideways only: stopped by a wall
+        if step_y == 0 {
+            state.velocity = (0., vy);
+            return Some((x, y));
+        }
 
-        // Check (x, y) first 
-        if (!self_air_like && has_behavior((x, y + dy), behaviors_grid, AIR_LIKE_ID)) || (self_air_like && grid[(y + dy) as usize][x as usize] == EMPTY_ID) {
-            return Some(0);
+        // Diagonally: keep going along one of the axes
+        if step_x != 0 {
+            if neighbourhood.out_of_reach((x, y + step_y)) || neighbourhood.out_of_reach((x + step_x, y)) {
+                return None;
+            }
+            if self.can_move_to(density, (x, y), (x, y + step_y), neighbourhood) {
+                state.velocity = (0., vy);
+                return Some((x, y + step_y));
+            }
+            if self.can_move_to(density, (x, y), (x + step_x, y), neighbourhood) {
+                state.velocity = (vx, 0.);
+                return Some((x + step_x, y));
+            }
+            state.velocity = (0., 0.);
+            return Some((x, y));
         }
 
-        // Otherwise, check both side, first choosen randomly
-        let dx = if rand::random::<f32>() < 0.5 { 1 } else { -1 };
-        let width = behaviors_grid[0].len() as i32;
-        if x + dx >= 0 && x + dx < width && ((!self_air_like && has_behavior((x + dx, y + dy), behaviors_grid, AIR_LIKE_ID)) || (self_air_like && grid[(y + dy) as usize][(x + dx) as usize] == EMPTY_ID)) {
-            return Some(dx);
+        // Vertically: slide down a side, first one chosen randomly, or turn part of the impact into a sideways motion
+        if neighbourhood.out_of_reach((x - 1, y + step_y)) || neighbourhood.out_of_reach((x + 1, y + step_y)) {
+            return None;
         }
+        let side = if rng.gen::<f32>() < 0.5 { 1 } else { -1 };
+        for dx in [side, -side] {
+            if self.can_move_to(density, (x, y), (x + dx, y + step_y), neighbourhood) {
+                return Some((x + dx, y + step_y));
+            }
+        }
+        let side = if vx != 0. { vx.signum() } else { side as f64 };
+        state.velocity = (vx + side * vy.abs() * IMPACT_TRANSFER, 0.);
+        Some((x, y))
+    }
 
-        if x - dx >= 0 && x - dx < width && ((!self_air_like && has_behavior((x - dx, y + dy), behaviors_grid, AIR_LIKE_ID)) || (self_air_like && grid[(y + dy) as usize][(x - dx) as usize] == EMPTY_ID)) {
-            return Some(-dx);
+    /// Going against its acceleration, e.g. when thrown by a blast, a particle only moves through empty cells.
+    /// Sideways, it goes through empty cells and lighter liquids and gases.
+    fn can_move_to(&self, density: f64, from: Position, to: Position, neighbourhood: &Neighbourhood) -> bool {
+        if !neighbourhood.in_bounds(to) {
+            return false;
         }
+        let empty = neighbourhood.particle_id(to) == EMPTY_ID;
+        match (to.1 - from.1).signum() {
+            direction if direction != 0 && direction == -(self.acceleration.signum() as i32) => empty,
+            direction => empty && direction == 0 || neighbourhood.can_displace_with(density, direction, to),
+        }
+    }
 
-        None
+    /// Whether none of the cells in the direction of the acceleration is available
+    fn is_blocked(&self, (x, y): Position, neighbourhood: &Neighbourhood) -> bool {
+        let density = neighbourhood.density((x, y));
+        let dy = self.acceleration.signum() as i32;
+        dy == 0 || (-1..=1).map(|dx| (x + dx, y + dy))
+            .all(|to| !neighbourhood.out_of_reach(to) && !self.can_move_to(density, (x, y), to, neighbourhood))
     }
 }
@@ -0,0 +1,43 @@
+use crate::sandsim::behaviors::*;
+
+/// `PhaseTransition` is a struct that implements the `Behavior` trait.
+/// This behavior turns the particle into another material when its temperature goes above or below a threshold,
+/// e.g. water boiling into steam. The temperature has to go `latent_heat` past the threshold, and the new particle
+/// starts at the threshold: the difference is the heat spent on the transition. With the threshold of the reverse
+/// transition on the other side, particles do not go back and forth between two materials.
+pub struct PhaseTransition {
+    threshold: f64,
+    rising: bool, // Whether the transition happens above the threshold, or below
+    into: ParticleId,
+    latent_heat: f64, // How far past the threshold the temperature must go (°C)
+}
+
+impl Behavior for PhaseTransition {
+    fn get_id(&self) -> BehaviorId {
+        PHASE_TRANSITION_ID
+    }
+
+    fn update(&mut self, state: &mut ParticleState, _dt: f64, _neighbourhood: &mut Neighbourhood, _rng: &mut SimRng) -> Vec<ParticleAction> {
+        let crossed = if self.rising {
+            state.temperature > self.threshold + self.latent_heat
+        } else {
+            state.temperature < self.threshold - self.latent_heat
+        };
+
+        if crossed {
+            vec![ParticleAction::Transform { material: self.into, temperature: self.threshold }]
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl PhaseTransition {
+    pub fn boxed_above(threshold: f64, into: ParticleId, latent_heat: f64) -> Box<dyn Behavior> {
+        Box::new(Self { threshold, rising: true, into, latent_heat })
+    }
+
+    pub fn boxed_below(threshold: f64, into: ParticleId, latent_heat: f64) -> Box<dyn Behavior> {
+        Box::new(Self { threshold, rising: false, into, latent_heat })
+    }
+}
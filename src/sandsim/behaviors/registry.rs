@@ -0,0 +1,119 @@
+use std::sync::{OnceLock, RwLock};
+
+/// Maximum number of behaviors, built-in ones included
+pub const MAX_BEHAVIORS: usize = BehaviorSet::BITS as usize;
+
+/// Handle of a behavior, allocated by the `BehaviorRegistry`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BehaviorId(u8);
+
+impl BehaviorId {
+    pub(crate) const fn builtin(index: u8) -> Self {
+        Self(index)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// `BehaviorSet` is a bitset of the behaviors of a particle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BehaviorSet(u128);
+
+impl BehaviorSet {
+    const BITS: u32 = u128::BITS;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn insert(&mut self, id: BehaviorId) {
+        self.0 |= 1 << id.0;
+    }
+
+    pub fn contains(&self, id: BehaviorId) -> bool {
+        self.0 & (1 << id.0) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BehaviorId> + '_ {
+        (0..Self::BITS as u8).map(BehaviorId).filter(|id| self.contains(*id))
+    }
+}
+
+impl FromIterator<BehaviorId> for BehaviorSet {
+    fn from_iter<I: IntoIterator<Item = BehaviorId>>(iter: I) -> Self {
+        let mut set = Self::empty();
+        for id in iter {
+            set.insert(id);
+        }
+        set
+    }
+}
+
+/// `BehaviorRegistry` allocates the ids of the behaviors, by name.
+/// The built-in behaviors are registered first, with the ids of the `*_ID` constants,
+/// other behaviors (e.g. from other crates) get the next free ids:
+///
+/// ```
+/// use std::sync::LazyLock;
+/// use sandgamebase::sandsim::behaviors::{BehaviorId, BehaviorRegistry};
+///
+/// static STICKY_ID: LazyLock<BehaviorId> = LazyLock::new(|| BehaviorRegistry::global().register("Sticky"));
+/// let sticky_id = *STICKY_ID;
+/// assert_eq!(BehaviorRegistry::global().id_of("Sticky"), Some(sticky_id));
+/// ```
+pub struct BehaviorRegistry {
+    names: RwLock<Vec<String>>, // Indexed by id
+}
+
+impl BehaviorRegistry {
+    fn new(builtin: &[(&str, BehaviorId)]) -> Self {
+        let registry = Self { names: RwLock::new(vec![]) };
+        for (name, id) in builtin {
+            assert_eq!(registry.register(name), *id, "Built-in behaviors must be registered in order");
+        }
+        registry
+    }
+
+    /// The registry shared by every grid
+    pub fn global() -> &'static BehaviorRegistry {
+        static REGISTRY: OnceLock<BehaviorRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| Self::new(&super::BUILTIN_BEHAVIORS))
+    }
+
+    /// Returns the id of the behavior, allocating one if the name was never registered.
+    /// Panics if there are already `MAX_BEHAVIORS` behaviors.
+    pub fn register(&self, name: &str) -> BehaviorId {
+        if let Some(id) = self.id_of(name) {
+            return id;
+        }
+
+        let mut names = self.names.write().unwrap();
+        // Another thread may have registered it in the meantime
+        if let Some(index) = names.iter().position(|other| other == name) {
+            return BehaviorId(index as u8);
+        }
+        assert!(names.len() < MAX_BEHAVIORS, "Cannot register the behavior \"{}\", there are already {} behaviors", name, MAX_BEHAVIORS);
+        names.push(name.to_string());
+        BehaviorId((names.len() - 1) as u8)
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<BehaviorId> {
+        let names = self.names.read().unwrap();
+        names.iter().position(|other| other == name).map(|index| BehaviorId(index as u8))
+    }
+
+    pub fn name_of(&self, id: BehaviorId) -> Option<String> {
+        self.names.read().unwrap().get(id.index()).cloned()
+    }
+
+    /// Names of the registered behaviors, indexed by id
+    pub fn names(&self) -> Vec<String> {
+        self.names.read().unwrap().clone()
+    }
+}
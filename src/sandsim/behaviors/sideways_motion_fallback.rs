@@ -1,3 +1,4 @@
+use rand::Rng;
 use crate::sandsim::behaviors::*;
 use crate::sandsim::grid::Position;
 
@@ -9,25 +10,29 @@ pub struct SidewaysMotionFallback {
 
 impl Behavior for SidewaysMotionFallback {
     fn get_id(&self) -> BehaviorId {
-        SIDEWAY_MOTION_FALLBACK
+        SIDEWAYS_MOTION_FALLBACK_ID
     }
 
-    fn update(&mut self, state: &mut ParticleState, _dt: f64, grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> Vec<ParticleAction> {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_i32(self.last_position.0);
+        writer.write_i32(self.last_position.1);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SnapshotError> {
+        self.last_position = (reader.read_i32()?, reader.read_i32()?);
+        Ok(())
+    }
+
+    fn update(&mut self, state: &mut ParticleState, _dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
         let mut actions = vec![];
 
-        if Self::are_all_downward_positions_blocked(state, grid, behaviors_grid) {
+        if Self::are_all_downward_positions_blocked(state, neighbourhood) {
             // Check if any of the sideways positions are empty
-            if let Some(new_position) = Self::get_empty_or_airlike_sideways_position(state, grid, behaviors_grid) {
+            if let Some(new_position) = Self::get_empty_or_airlike_sideways_position(state, neighbourhood, rng) {
                 
-                // Swap particle IDs
-                grid[new_position.1 as usize][new_position.0 as usize] = grid[state.position.1 as usize][state.position.0 as usize];
-                grid[state.position.1 as usize][state.position.0 as usize] = EMPTY_ID;
+                // Swap particle and behaviors IDs
+                neighbourhood.swap(state.position, new_position);
 
-                // Swap behaviors IDs
-                let tmp = behaviors_grid[new_position.1 as usize][new_position.0 as usize];
-                behaviors_grid[new_position.1 as usize][new_position.0 as usize] = behaviors_grid[state.position.1 as usize][state.position.0 as usize];
-                behaviors_grid[state.position.1 as usize][state.position.0 as usize] = tmp;
-                
                 actions.push(ParticleAction::SetPosition { position: new_position });
                 state.position = new_position;
             }
@@ -40,37 +45,32 @@ impl Behavior for SidewaysMotionFallback {
 
 impl SidewaysMotionFallback {
     pub fn boxed(position: &Position) -> Box<dyn Behavior> {
-        Box::new(Self {last_position: position.clone()})
+        Box::new(Self {last_position: *position})
     }
 
-    fn is_empty_or_airlike(position: Position, grid: &Vec<Vec<ParticleId>>, behaviors_grid: &Vec<Vec<BehaviorId>>) -> bool {
-        let grid_height = grid.len() as i32;
-        let grid_width = grid[0].len() as i32;
-
-        if position.0 < 0 || position.0 >= grid_width || position.1 < 0 || position.1 >= grid_height {
+    fn is_empty_or_airlike(position: Position, neighbourhood: &Neighbourhood) -> bool {
+        if !neighbourhood.in_bounds(position) {
             return false;
         }
 
-        let particle = grid[position.1 as usize][position.0 as usize];
-        particle == EMPTY_ID || has_behavior(position, behaviors_grid, AIR_LIKE_ID)
+        neighbourhood.particle_id(position) == EMPTY_ID || neighbourhood.has_behavior(position, AIR_LIKE_ID)
     }
 
-    fn are_all_downward_positions_blocked(state: &mut ParticleState, grid: &Vec<Vec<ParticleId>>, behaviors_grid: &Vec<Vec<BehaviorId>>) -> bool {
-        let height = grid.len() as i32;
-        if state.position.1 + 1 >= height {
+    fn are_all_downward_positions_blocked(state: &mut ParticleState, neighbourhood: &Neighbourhood) -> bool {
+        if state.position.1 + 1 >= neighbourhood.height() {
             return true;
         }
         
-        !Self::is_empty_or_airlike((state.position.0, state.position.1 + 1), grid, behaviors_grid) &&
-        !Self::is_empty_or_airlike((state.position.0 - 1, state.position.1 + 1), grid, behaviors_grid) &&
-        !Self::is_empty_or_airlike((state.position.0 + 1, state.position.1 + 1), grid, behaviors_grid)
+        !Self::is_empty_or_airlike((state.position.0, state.position.1 + 1), neighbourhood) &&
+        !Self::is_empty_or_airlike((state.position.0 - 1, state.position.1 + 1), neighbourhood) &&
+        !Self::is_empty_or_airlike((state.position.0 + 1, state.position.1 + 1), neighbourhood)
     }
 
-    fn get_empty_or_airlike_sideways_position(state: &mut ParticleState, grid: &Vec<Vec<ParticleId>>, behaviors_grid: &Vec<Vec<BehaviorId>>) -> Option<Position> {
-        let dx = if rand::random() { -1 } else { 1 };
-        if Self::is_empty_or_airlike((state.position.0 - dx, state.position.1), grid, behaviors_grid) {
+    fn get_empty_or_airlike_sideways_position(state: &mut ParticleState, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Option<Position> {
+        let dx = if rng.gen() { -1 } else { 1 };
+        if Self::is_empty_or_airlike((state.position.0 - dx, state.position.1), neighbourhood) {
             return Some((state.position.0 - dx, state.position.1));
-        } else if Self::is_empty_or_airlike((state.position.0 + dx, state.position.1), grid, behaviors_grid) {
+        } else if Self::is_empty_or_airlike((state.position.0 + dx, state.position.1), neighbourhood) {
             return Some((state.position.0 + dx, state.position.1));
         }
 
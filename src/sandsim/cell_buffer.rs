@@ -0,0 +1,231 @@
+use std::ops::{Index, IndexMut};
+
+use crate::sandsim::chunks::DirtyRect;
+use crate::sandsim::grid::Position;
+
+/// `CellBuffer` stores one value per grid cell in a single flat, row-major buffer.
+/// Cell `(x, y)` lives at index `y * width + x`.
+#[derive(Clone)]
+pub struct CellBuffer<T> {
+    width: i32,
+    height: i32,
+    data: Vec<T>,
+}
+
+impl<T> CellBuffer<T> {
+    pub fn new(width: i32, height: i32, mut default: impl FnMut(Position) -> T) -> Self {
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(default((x, y)));
+            }
+        }
+        Self { width, height, data }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, (x, y): Position) -> bool {
+        x >= 0 && x < self.width && y >= 0 && y < self.height
+    }
+
+    /// Index of the given position in the flat buffer. The position must be in bounds.
+    pub fn index_of(&self, (x, y): Position) -> usize {
+        debug_assert!(self.in_bounds((x, y)), "Position {:?} is out of bounds", (x, y));
+        (y * self.width + x) as usize
+    }
+
+    /// Position of the given index of the flat buffer
+    pub fn position_of(&self, index: usize) -> Position {
+        (index as i32 % self.width, index as i32 / self.width)
+    }
+
+    pub fn get(&self, position: Position) -> Option<&T> {
+        if self.in_bounds(position) {
+            Some(&self.data[self.index_of(position)])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, position: Position) -> Option<&mut T> {
+        if self.in_bounds(position) {
+            let index = self.index_of(position);
+            Some(&mut self.data[index])
+        } else {
+            None
+        }
+    }
+
+    pub fn swap(&mut self, a: Position, b: Position) {
+        let (a, b) = (self.index_of(a), self.index_of(b));
+        self.data.swap(a, b);
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.data.iter()
+    }
+
+    /// Mutable window over the whole buffer
+    pub fn window_mut(&mut self) -> CellWindow<'_, T> {
+        let bounds = DirtyRect::new((0, 0), (self.width - 1, self.height - 1));
+        let (width, height) = (self.width, self.height);
+        CellWindow {
+            bounds,
+            width,
+            height,
+            rows: self.data.chunks_mut(width.max(1) as usize).collect(),
+        }
+    }
+
+    /// Mutable windows over each of the given rectangles, which must not overlap.
+    /// The rectangles are clipped to the buffer.
+    pub fn split_windows_mut(&mut self, rects: &[DirtyRect]) -> Vec<CellWindow<'_, T>> {
+        let (width, height) = (self.width, self.height);
+        let whole = DirtyRect::new((0, 0), (width - 1, height - 1));
+        let mut windows: Vec<CellWindow<T>> = rects.iter()
+            .map(|rect| CellWindow {
+                bounds: rect.intersection(&whole).unwrap_or(DirtyRect::new((0, 0), (-1, -1))),
+                width,
+                height,
+                rows: vec![],
+            })
+            .collect();
+
+        // Sort the windows from left to right, to cut each row in order
+        let mut order: Vec<usize> = (0..windows.len()).collect();
+        order.sort_by_key(|&i| windows[i].bounds.min_x);
+
+        for (y, row) in self.data.chunks_mut(width.max(1) as usize).enumerate() {
+            let y = y as i32;
+            let mut rest = row;
+            let mut offset = 0;
+            for &i in &order {
+                let bounds = windows[i].bounds;
+                if y < bounds.min_y || y > bounds.max_y {
+                    continue;
+                }
+                assert!(bounds.min_x >= offset, "Windows must not overlap");
+
+                let (_, tail) = rest.split_at_mut((bounds.min_x - offset) as usize);
+                let (segment, tail) = tail.split_at_mut((bounds.max_x - bounds.min_x + 1) as usize);
+                windows[i].rows.push(segment);
+                rest = tail;
+                offset = bounds.max_x + 1;
+            }
+        }
+
+        windows
+    }
+}
+
+impl<T> Index<Position> for CellBuffer<T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &T {
+        &self.data[self.index_of(position)]
+    }
+}
+
+impl<T> IndexMut<Position> for CellBuffer<T> {
+    fn index_mut(&mut self, position: Position) -> &mut T {
+        let index = self.index_of(position);
+        &mut self.data[index]
+    }
+}
+
+/// `CellWindow` gives mutable access to a rectangle of a `CellBuffer`, using the positions of the whole buffer.
+/// Windows over disjoint rectangles can be used at the same time, e.g. from different threads.
+pub struct CellWindow<'a, T> {
+    bounds: DirtyRect,
+    width: i32, // Of the whole buffer
+    height: i32, // Of the whole buffer
+    rows: Vec<&'a mut [T]>,
+}
+
+impl<T> CellWindow<'_, T> {
+    /// Width of the whole buffer
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    /// Height of the whole buffer
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn bounds(&self) -> DirtyRect {
+        self.bounds
+    }
+
+    /// Whether the position is inside the window
+    pub fn in_bounds(&self, position: Position) -> bool {
+        self.bounds.contains(position)
+    }
+
+    pub fn get(&self, position: Position) -> Option<&T> {
+        if self.in_bounds(position) {
+            let (x, y) = self.local(position);
+            Some(&self.rows[y][x])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, position: Position) -> Option<&mut T> {
+        if self.in_bounds(position) {
+            let (x, y) = self.local(position);
+            Some(&mut self.rows[y][x])
+        } else {
+            None
+        }
+    }
+
+    pub fn swap(&mut self, a: Position, b: Position) {
+        let (ax, ay) = self.local(a);
+        let (bx, by) = self.local(b);
+        if ay == by {
+            self.rows[ay].swap(ax, bx);
+        } else {
+            let ((top_x, top_y), (bottom_x, bottom_y)) = if ay < by { ((ax, ay), (bx, by)) } else { ((bx, by), (ax, ay)) };
+            let (top, bottom) = self.rows.split_at_mut(bottom_y);
+            std::mem::swap(&mut top[top_y][top_x], &mut bottom[0][bottom_x]);
+        }
+    }
+
+    /// Position relative to the window. The position must be in the window.
+    fn local(&self, position: Position) -> (usize, usize) {
+        assert!(self.in_bounds(position), "Position {:?} is outside of the window {:?}", position, self.bounds);
+        ((position.0 - self.bounds.min_x) as usize, (position.1 - self.bounds.min_y) as usize)
+    }
+}
+
+impl<T> Index<Position> for CellWindow<'_, T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &T {
+        let (x, y) = self.local(position);
+        &self.rows[y][x]
+    }
+}
+
+impl<T> IndexMut<Position> for CellWindow<'_, T> {
+    fn index_mut(&mut self, position: Position) -> &mut T {
+        let (x, y) = self.local(position);
+        &mut self.rows[y][x]
+    }
+}
@@ -0,0 +1,222 @@
+use crate::sandsim::grid::Position;
+
+/// Side of a chunk, in cells
+pub const CHUNK_SIZE: i32 = 16;
+/// When a cell changes, every cell within this distance is woken up.
+/// Must be at least the largest distance at which a behavior looks at its neighbours (e.g. the `Flammable` radius).
+pub const WAKE_RADIUS: i32 = 3;
+
+/// Inclusive rectangle of cells
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl DirtyRect {
+    pub fn new((min_x, min_y): Position, (max_x, max_y): Position) -> Self {
+        Self { min_x, min_y, max_x, max_y }
+    }
+
+    pub fn around((x, y): Position, radius: i32) -> Self {
+        Self::new((x - radius, y - radius), (x + radius, y + radius))
+    }
+
+    pub fn contains(&self, (x, y): Position) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+
+    pub fn union(&self, other: &DirtyRect) -> DirtyRect {
+        DirtyRect {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn intersection(&self, other: &DirtyRect) -> Option<DirtyRect> {
+        let res = DirtyRect {
+            min_x: self.min_x.max(other.min_x),
+            min_y: self.min_y.max(other.min_y),
+            max_x: self.max_x.min(other.max_x),
+            max_y: self.max_y.min(other.max_y),
+        };
+        if res.min_x <= res.max_x && res.min_y <= res.max_y {
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    /// Every position in the rectangle, row by row
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (min_x, max_x) = (self.min_x, self.max_x);
+        (self.min_y..=self.max_y).flat_map(move |y| (min_x..=max_x).map(move |x| (x, y)))
+    }
+}
+
+/// A change to apply to the `ChunkMap`, recorded while the cells are being updated
+#[derive(Clone, Copy, Debug)]
+pub enum ChunkEvent {
+    Wake(Position),
+    KeepAwake(Position),
+    Draw(Position),
+}
+
+#[derive(Clone, Default)]
+struct Chunk {
+    update_rect: Option<DirtyRect>, // Cells to update during the current tick
+    next_update_rect: Option<DirtyRect>, // Cells to update during the next tick
+    draw_rect: Option<DirtyRect>, // Cells to redraw
+}
+
+/// `ChunkMap` splits the grid in `CHUNK_SIZE` x `CHUNK_SIZE` chunks, each one tracking the rectangles of cells
+/// that need to be updated and redrawn. Chunks without any such rectangle are asleep and cost nothing.
+pub struct ChunkMap {
+    width: i32,
+    height: i32,
+    columns: i32,
+    rows: i32,
+    chunks: Vec<Chunk>,
+}
+
+impl ChunkMap {
+    pub fn new(width: i32, height: i32) -> Self {
+        let columns = (width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let rows = (height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let mut res = Self {
+            width,
+            height,
+            columns,
+            rows,
+            chunks: vec![Chunk::default(); (columns * rows) as usize],
+        };
+        res.wake_all();
+        res
+    }
+
+    /// Number of chunks in a row of chunks
+    pub fn columns(&self) -> i32 {
+        self.columns
+    }
+
+    /// Number of chunks in a column of chunks
+    pub fn rows(&self) -> i32 {
+        self.rows
+    }
+
+    /// Number of chunks that will be updated during the next tick
+    pub fn awake_chunks(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.next_update_rect.is_some()).count()
+    }
+
+    /// Cells covered by the given chunk
+    pub fn chunk_bounds(&self, (chunk_x, chunk_y): Position) -> DirtyRect {
+        DirtyRect::new(
+            (chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE),
+            (((chunk_x + 1) * CHUNK_SIZE).min(self.width) - 1, ((chunk_y + 1) * CHUNK_SIZE).min(self.height) - 1))
+    }
+
+    /// Marks the whole grid to be updated and redrawn
+    pub fn wake_all(&mut self) {
+        for chunk_y in 0..self.rows {
+            for chunk_x in 0..self.columns {
+                let bounds = Some(self.chunk_bounds((chunk_x, chunk_y)));
+                let chunk = &mut self.chunks[(chunk_y * self.columns + chunk_x) as usize];
+                chunk.update_rect = bounds;
+                chunk.next_update_rect = bounds;
+                chunk.draw_rect = bounds;
+            }
+        }
+    }
+
+    /// A cell changed: its neighbours (within `WAKE_RADIUS`) are updated for the rest of this tick and during the next one
+    pub fn wake(&mut self, position: Position) {
+        self.merge(DirtyRect::around(position, WAKE_RADIUS), |chunk| &mut chunk.update_rect);
+        self.merge(DirtyRect::around(position, WAKE_RADIUS), |chunk| &mut chunk.next_update_rect);
+    }
+
+    /// The particle in this cell is still active, update it during the next tick
+    pub fn keep_awake(&mut self, position: Position) {
+        self.merge(DirtyRect::around(position, 0), |chunk| &mut chunk.next_update_rect);
+    }
+
+    pub fn mark_to_draw(&mut self, position: Position) {
+        self.merge(DirtyRect::around(position, 0), |chunk| &mut chunk.draw_rect);
+    }
+
+    pub fn apply(&mut self, event: ChunkEvent) {
+        match event {
+            ChunkEvent::Wake(position) => self.wake(position),
+            ChunkEvent::KeepAwake(position) => self.keep_awake(position),
+            ChunkEvent::Draw(position) => self.mark_to_draw(position),
+        }
+    }
+
+    /// Starts a new tick: what was scheduled for the next tick becomes the current work
+    pub fn begin_tick(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.update_rect = chunk.next_update_rect.take();
+        }
+    }
+
+    /// Cells of each chunk to update during the next tick
+    pub fn next_update_rects(&self) -> Vec<Option<DirtyRect>> {
+        self.chunks.iter().map(|chunk| chunk.next_update_rect).collect()
+    }
+
+    /// Replaces the cells to update during the next tick, as returned by `next_update_rects`, and redraws everything
+    pub fn restore_next_update_rects(&mut self, rects: &[Option<DirtyRect>]) {
+        self.wake_all();
+        for (chunk, rect) in self.chunks.iter_mut().zip(rects) {
+            chunk.update_rect = None;
+            chunk.next_update_rect = *rect;
+        }
+    }
+
+    /// Cells of the given chunk to update during the current tick, if any
+    pub fn update_rect(&self, (chunk_x, chunk_y): Position) -> Option<DirtyRect> {
+        self.chunks[(chunk_y * self.columns + chunk_x) as usize].update_rect
+    }
+
+    /// The range of x to update in the given row of the given chunk, if any
+    pub fn update_span(&self, chunk_x: i32, y: i32) -> Option<(i32, i32)> {
+        let chunk = &self.chunks[((y / CHUNK_SIZE) * self.columns + chunk_x) as usize];
+        chunk.update_rect
+            .filter(|rect| y >= rect.min_y && y <= rect.max_y)
+            .map(|rect| (rect.min_x, rect.max_x))
+    }
+
+    /// Whether the cell is updated during the current tick
+    pub fn is_updated(&self, (x, y): Position) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+            && self.update_span(x / CHUNK_SIZE, y).is_some_and(|(min_x, max_x)| x >= min_x && x <= max_x)
+    }
+
+    /// Returns the rectangles to redraw, and forgets about them
+    pub fn take_draw_rects(&mut self) -> Vec<DirtyRect> {
+        self.chunks.iter_mut().filter_map(|chunk| chunk.draw_rect.take()).collect()
+    }
+
+    fn merge(&mut self, rect: DirtyRect, field: fn(&mut Chunk) -> &mut Option<DirtyRect>) {
+        let Some(rect) = rect.intersection(&DirtyRect::new((0, 0), (self.width - 1, self.height - 1))) else {
+            return;
+        };
+
+        for chunk_y in rect.min_y / CHUNK_SIZE..=rect.max_y / CHUNK_SIZE {
+            for chunk_x in rect.min_x / CHUNK_SIZE..=rect.max_x / CHUNK_SIZE {
+                let Some(part) = rect.intersection(&self.chunk_bounds((chunk_x, chunk_y))) else {
+                    continue;
+                };
+                let slot = field(&mut self.chunks[(chunk_y * self.columns + chunk_x) as usize]);
+                *slot = Some(match slot {
+                    Some(current) => current.union(&part),
+                    None => part,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+pub const DEFAULT_TICK_RATE: f64 = 60.;
+pub const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 16;
+pub const MIN_TIME_SCALE: f64 = 0.25;
+pub const MAX_TIME_SCALE: f64 = 8.;
+
+/// `SimulationClock` turns real elapsed time into a number of fixed simulation ticks.
+/// Elapsed time (multiplied by the time scale) is accumulated, and every full `dt` worth of it yields one tick,
+/// so the simulation speed does not depend on the rendering frame rate.
+/// A time scale of zero means the simulation is paused, in which case it only advances through `step`.
+pub struct SimulationClock {
+    tick_rate: f64, // Number of ticks per simulated second
+    max_ticks_per_frame: u32, // Above this, the remaining time is dropped to avoid a spiral of death
+    time_scale: f64,
+    resume_time_scale: f64, // The time scale to go back to when unpausing
+
+    accumulator: f64, // Simulated time not consumed by a tick yet (Unit: second)
+    pending_steps: u32, // Single steps requested while paused
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_RATE, DEFAULT_MAX_TICKS_PER_FRAME)
+    }
+}
+
+impl SimulationClock {
+    pub fn new(tick_rate: f64, max_ticks_per_frame: u32) -> Self {
+        assert!(tick_rate > 0., "The tick rate must be strictly positive");
+        Self {
+            tick_rate,
+            max_ticks_per_frame: max_ticks_per_frame.max(1),
+            time_scale: 1.,
+            resume_time_scale: 1.,
+
+            accumulator: 0.,
+            pending_steps: 0,
+        }
+    }
+
+    /// Duration of a single tick, to pass to `Grid::update`
+    pub fn dt(&self) -> f64 {
+        1. / self.tick_rate
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        self.tick_rate
+    }
+
+    pub fn set_tick_rate(&mut self, tick_rate: f64) {
+        assert!(tick_rate > 0., "The tick rate must be strictly positive");
+        self.tick_rate = tick_rate;
+        self.accumulator = 0.;
+    }
+
+    pub fn set_max_ticks_per_frame(&mut self, max_ticks_per_frame: u32) {
+        self.max_ticks_per_frame = max_ticks_per_frame.max(1);
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Sets the time scale, clamped between `MIN_TIME_SCALE` and `MAX_TIME_SCALE`.
+    /// A time scale of zero pauses the simulation.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        if time_scale <= 0. {
+            self.pause();
+            return;
+        }
+
+        self.time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.resume_time_scale = self.time_scale;
+    }
+
+    /// Doubles the time scale (resumes the simulation if it was paused)
+    pub fn speed_up(&mut self) {
+        self.set_time_scale(self.resume_time_scale * 2.);
+    }
+
+    /// Halves the time scale (resumes the simulation if it was paused)
+    pub fn slow_down(&mut self) {
+        self.set_time_scale(self.resume_time_scale / 2.);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.time_scale == 0.
+    }
+
+    pub fn pause(&mut self) {
+        if !self.is_paused() {
+            self.resume_time_scale = self.time_scale;
+        }
+        self.time_scale = 0.;
+        self.accumulator = 0.;
+    }
+
+    pub fn resume(&mut self) {
+        self.time_scale = self.resume_time_scale;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.is_paused() {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    /// Requests a single tick on the next call to `advance`. Only has an effect while paused.
+    pub fn step(&mut self) {
+        if self.is_paused() {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// Consumes `elapsed` seconds of real time and returns the number of ticks to simulate
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        let steps = std::mem::take(&mut self.pending_steps);
+        if self.is_paused() {
+            return steps.min(self.max_ticks_per_frame);
+        }
+
+        self.accumulator += elapsed.max(0.) * self.time_scale;
+        let dt = self.dt();
+        let ticks = (self.accumulator / dt).floor() as u32;
+
+        if ticks > self.max_ticks_per_frame {
+            // We are too far behind, drop the backlog instead of trying to catch up
+            self.accumulator = 0.;
+            self.max_ticks_per_frame
+        } else {
+            self.accumulator -= ticks as f64 * dt;
+            ticks
+        }
+    }
+}
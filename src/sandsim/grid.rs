@@ -1,70 +1,144 @@
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-
-use std::collections::HashSet;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
 
+use crate::color::Color;
 use crate::sandsim::particle::*;
 use crate::sandsim::brush_settings::BrushSettings;
-use crate::sandsim::particle_action::ParticleAction;
-use crate::sandsim::behaviors::BehaviorId;
+use crate::sandsim::behaviors::BehaviorSet;
+use crate::sandsim::cell_buffer::CellBuffer;
+use crate::sandsim::chunks::ChunkMap;
+use crate::sandsim::material::MaterialRegistry;
+use crate::sandsim::region::Region;
+use structures::FallingBody;
+
+#[cfg(feature = "parallel")]
+mod parallel;
+mod snapshot;
+mod level;
+mod render;
+mod heat;
+mod pressure;
+mod reactions;
+mod structures;
 
 pub type Position = (i32, i32);
-pub const PIXEL_SIZE: i32 = 5;
+
+/// The random number generator used by the whole simulation.
+/// It is owned by the `Grid` and lent to every behavior and particle constructor,
+/// so that a given seed and the same inputs always produce the same grid.
+/// This is the generator behind `rand`'s `StdRng`, used directly so that its state can be saved in snapshots.
+pub type SimRng = rand_chacha::ChaCha12Rng;
 
 pub struct Grid {
     pub width: i32,
     pub height: i32,
-    pub cells: Vec<Vec<Particle>>,
-    // pub cell_types: Vec<Vec<ParticleId>>,
-    pub cells_to_draw: HashSet<(i32, i32)>,
+    pub cells: CellBuffer<Particle>,
+    materials: Arc<MaterialRegistry>, // Shared, so that several grids can use the same definitions
+
+    // Particle and behavior ids of every cell, kept in sync with `cells` and handed to the behaviors
+    cell_types: CellBuffer<ParticleId>,
+    cell_behaviors: CellBuffer<BehaviorSet>,
+    // Which parts of the grid need to be updated or redrawn
+    chunks: ChunkMap,
+    tick: u64,
+    // Structures falling as one piece, see `collapse_structures`
+    bodies: Vec<FallingBody>,
+    structure_visits: CellBuffer<u64>, // Tick of the last visit of each cell
+    structure_types: CellBuffer<ParticleId>, // Particle id of each cell when structures were last checked
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+
+    seed: u64,
+    rng: SimRng,
 }
 
 #[allow(dead_code)]
 impl Grid {
-    pub fn new(window_width: i32, window_height: i32) -> Grid {
-        let width = window_width / PIXEL_SIZE;
-        let height = window_height / PIXEL_SIZE;
-        let cells = Self::make_grid(width, height, |pos| Particle::new_empty(pos));
-        // let cell_types = Self::make_grid(width, height, |_pos| EMPTY_ID);
-        Grid {
+    pub fn new(width: i32, height: i32) -> Grid {
+        Self::with_seed(width, height, rand::random())
+    }
+
+    pub fn with_seed(width: i32, height: i32, seed: u64) -> Grid {
+        Self::with_materials(width, height, seed, Arc::new(MaterialRegistry::builtin()))
+    }
+
+    /// Creates a grid using the given materials instead of the built-in ones
+    pub fn with_materials(width: i32, height: i32, seed: u64, materials: Arc<MaterialRegistry>) -> Grid {
+        let mut rng = SimRng::seed_from_u64(seed);
+        let cells = CellBuffer::new(width, height, |pos| materials.create(EMPTY_ID, pos, &mut rng));
+        let mut grid = Grid {
             width,
             height,
             cells,
-            // cell_types,
-            cells_to_draw: HashSet::new(),
-        }
+            materials,
+
+            cell_types: CellBuffer::new(width, height, |_pos| EMPTY_ID),
+            cell_behaviors: CellBuffer::new(width, height, |_pos| BehaviorSet::empty()),
+            chunks: ChunkMap::new(width, height),
+            tick: 0,
+            bodies: vec![],
+            structure_visits: CellBuffer::new(width, height, |_pos| 0),
+            structure_types: CellBuffer::new(width, height, |_pos| EMPTY_ID),
+            #[cfg(feature = "parallel")]
+            parallel: false,
+
+            seed,
+            rng,
+        };
+        grid.refresh_cell_ids();
+        grid
     }
 
-    fn make_grid<T>(width: i32, height: i32, default: fn(Position) -> T) -> Vec<Vec<T>> {
-        let mut res = Vec::with_capacity(height as usize);
-        for y in 0..height {
-            let mut row = Vec::with_capacity(width as usize);
-            for x in 0..width {
-                row.push(default((x, y)));
-            }
-            res.push(row);
-        }
-        res
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+
+    pub fn materials(&self) -> &Arc<MaterialRegistry> {
+        &self.materials
+    }
+
+    pub fn chunks(&self) -> &ChunkMap {
+        &self.chunks
+    }
+
+    /// Number of updates since the creation of the grid
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Makes `update` process the chunks on several threads (see `update_parallel`)
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
     }
 
     pub fn clear(&mut self) {
-        self.cells = Self::make_grid(self.width, self.height, |pos| Particle::new_empty(pos));
-        // self.cell_types = Self::make_grid(self.width, self.height, |_pos| EMPTY_ID);
-        for y in 0..self.height {
-            for x in 0..self.width {
-                self.cells_to_draw.insert((x, y));
-            }
-        }
+        let (rng, materials) = (&mut self.rng, &self.materials);
+        self.cells = CellBuffer::new(self.width, self.height, |pos| materials.create(EMPTY_ID, pos, rng));
+        self.refresh_cell_ids();
+        self.bodies.clear();
+        self.chunks.wake_all();
     }
 
-    pub fn set(&mut self, (x, y): Position, value: Particle) {
-        if y >= self.height || x >= self.width || y < 0 || x < 0{
-            return;
+    pub fn set(&mut self, position: Position, value: Particle) {
+        if let Some(cell) = self.cells.get_mut(position) {
+            *cell = value;
+            self.sync_cell_ids(position);
+            self.chunks.wake(position);
+            self.chunks.mark_to_draw(position);
         }
+    }
 
-        // self.cell_types[y as usize][x as usize] = value.get_id();
-        self.cells[y as usize][x as usize]= value;
-        self.cells_to_draw.insert((x, y));
+    /// Replaces the particle at the given position by a new particle of the given material
+    pub fn spawn(&mut self, position: Position, material: ParticleId) {
+        if self.cells.in_bounds(position) {
+            let particle = self.materials.create(material, position, &mut self.rng);
+            self.set(position, particle);
+        }
     }
 
     pub fn set_circle(&mut self, (x, y): Position, brush_settings: &BrushSettings) {
@@ -73,138 +147,121 @@ impl Grid {
                 if i * i + j * j <= brush_settings.size * brush_settings.size {
                     let new_x = x + i;
                     let new_y = y + j;
-                    let spawn = rand::random::<f32>() < brush_settings.probability;
-                    if spawn && new_x >= 0 && new_x < self.width && new_y >= 0 && new_y < self.height {
-                        let particle = (brush_settings.callback)((new_x, new_y));
-                        if particle.get_id() == EMPTY_ID || self.get_particle_id((new_x, new_y)) == EMPTY_ID {
-                            self.set((new_x, new_y), particle);
-                        }
+                    let spawn = self.rng.gen::<f32>() < brush_settings.probability;
+                    let in_bounds = new_x >= 0 && new_x < self.width && new_y >= 0 && new_y < self.height;
+                    if spawn && in_bounds && (brush_settings.material == EMPTY_ID || self.is_empty((new_x, new_y))) {
+                        self.spawn((new_x, new_y), brush_settings.material);
                     }
                 }
             }
         }
     }
 
-    pub fn get_mut(&mut self, (x, y): Position) -> &mut Particle {
-        &mut self.cells[y as usize][x as usize]
+    /// Changes made to the particle through this reference are not tracked, call `wake` afterwards if needed
+    pub fn get_mut(&mut self, position: Position) -> &mut Particle {
+        &mut self.cells[position]
     }
 
-    pub fn get(&self, (x, y): Position) -> &Particle {
-        &self.cells[y as usize][x as usize]
+    pub fn get(&self, position: Position) -> &Particle {
+        &self.cells[position]
     }
 
     pub fn get_particle_id(&self, (x, y): Position) -> ParticleId {
         self.get((x, y)).get_id()
     }
 
-    pub fn swap(&mut self, (mut x1, mut y1): Position, (mut x2, mut y2): Position) {
-        if self.is_empty((x1, y1)) && self.is_empty((x2, y2)) { return; }
+    pub fn swap(&mut self, a: Position, b: Position) {
+        if self.is_empty(a) && self.is_empty(b) { return; }
 
-        // Swap the particles
-        if y1 == y2 {
-            self.cells[y1 as usize].swap(x1 as usize, x2 as usize);
-        } else {
-            if y2 < y1 {
-                (y1, y2) = (y2, y1);
-                (x1, x2) = (x2, x1);
-            }
+        self.cells.swap(a, b);
+        self.cell_types.swap(a, b);
+        self.cell_behaviors.swap(a, b);
 
-            let (a, b) = self.cells.split_at_mut(y2 as usize);
-            std::mem::swap(&mut a[y1 as usize][x1 as usize], &mut b[0][x2 as usize]);
+        // Force the update and redraw on both
+        for position in [a, b] {
+            self.chunks.wake(position);
+            self.chunks.mark_to_draw(position);
         }
+    }
 
-        // Swap the types
-        // let tmp = self.cell_types[y1 as usize][x1 as usize];
-        // self.cell_types[y1 as usize][x1 as usize] = self.cell_types[y2 as usize][x2 as usize];
-        // self.cell_types[y2 as usize][x2 as usize] = tmp;
-
-        // Force the redraw on both
-        self.cells_to_draw.insert((x1, y1));
-        self.cells_to_draw.insert((x2, y2));
+    /// Forces the update and redraw of the given cell and its neighbours
+    pub fn wake(&mut self, position: Position) {
+        self.sync_cell_ids(position);
+        self.chunks.wake(position);
+        self.chunks.mark_to_draw(position);
     }
 
     pub fn is_empty(&self, (x, y): Position) -> bool {
         self.get_particle_id((x, y)) == EMPTY_ID
     }
 
-    pub fn draw(&mut self, canvas: &mut Canvas<Window>) {
-        let pos: Vec<_> = self.cells_to_draw.iter().cloned().collect();
-        for (x, y) in pos {
-            let color = self.get((x, y)).get_color();
-            let rect = sdl2::rect::Rect::new(x * PIXEL_SIZE, y * PIXEL_SIZE, PIXEL_SIZE as u32, PIXEL_SIZE as u32);
-            canvas.set_draw_color(color);
-            canvas.fill_rect(rect).unwrap();
-        }
-        self.cells_to_draw.clear();
+    /// Returns every cell that changed since the last call, along with its current color.
+    /// Renderers are expected to repaint exactly these cells.
+    pub fn take_cells_to_draw(&mut self) -> Vec<(Position, Color)> {
+        self.chunks.take_draw_rects()
+            .iter()
+            .flat_map(|rect| rect.positions())
+            .map(|position| (position, self.get(position).get_color()))
+            .collect()
     }
 
+    /// Advances the simulation by `dt` seconds: particles, then reactions (see `react`), falling structures (see `collapse_structures`), heat (see `diffuse_heat`) and gases (see `spread_pressure`).
+    /// Only the awake parts of the grid are updated.
     pub fn update(&mut self, dt: f64) {
-        let mut cell_types = self.build_cell_types();
-        let mut cell_behaviors = self.build_cell_behaviors();
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            self.update_parallel(dt);
+            return;
+        }
 
-        // let mut cell_types = self.cell_types.clone();
-        for y in (0..self.height).rev() {
-            let (mut x, step) = {
-                if rand::random::<f32>() < 0.5 {
-                    (0, 1)
-                } else {
-                    (self.width - 1, -1)
-                }
-            };
-
-            while x >= 0 && x < self.width {
-                // Swaps are relative to the current cell
-                let modified = self.get_mut((x, y)).update((x, y), dt, &mut cell_types, &mut cell_behaviors);
-                
-                if modified {
-                    // Handle particle actions
-                    let actions = self.get((x, y)).get_required_actions();
-                    for action in actions {
-                        match action {
-                            ParticleAction::KillParticle { position } => {
-                                self.set(position, Particle::new_empty(position));
-                            },
-                            ParticleAction::SpawnParticle { position, callback} => {
-                                if self.get_particle_id(position) == EMPTY_ID {
-                                    self.set(position, callback(position));
-                                }
-                            }
-                            _ => panic!("Action should be handled by the particle, not the grid"),
-                        }
-                    }
+        self.update_serial(dt);
+    }
 
-                    // Update the position if needed
-                    let new_position = self.get((x, y)).get_position();
-                    if new_position != (x, y) {
-                        self.swap((x, y), new_position);
-                    }
+    /// Updates the awake cells one after the other, row by row from the bottom of the grid
+    pub fn update_serial(&mut self, dt: f64) {
+        self.tick += 1;
+        self.chunks.begin_tick();
+
+        let mut region = Region::new(self.cells.window_mut(), self.cell_types.window_mut(), self.cell_behaviors.window_mut(), &self.materials, self.tick);
+        let columns = self.chunks.columns();
+        for y in (0..self.height).rev() {
+            let forward = self.rng.gen::<f32>() < 0.5;
 
-                    self.cells_to_draw.insert((x, y));
-                } 
-                
+            for i in 0..columns {
+                let chunk_x = if forward { i } else { columns - 1 - i };
+                let Some((min_x, max_x)) = self.chunks.update_span(chunk_x, y) else {
+                    continue;
+                };
 
-                x += step;
+                let (mut x, step) = if forward { (min_x, 1) } else { (max_x, -1) };
+                while x >= min_x && x <= max_x {
+                    region.update_cell((x, y), dt, &mut self.rng);
+                    region.apply_events(&mut self.chunks);
+                    x += step;
+                }
             }
         }
+
+        self.react(dt);
+        self.collapse_structures(dt);
+        self.diffuse_heat(dt);
+        self.spread_pressure();
     }
 
-    fn build_cell_types(&self) -> Vec<Vec<ParticleId>> {
-        let mut res = vec![vec![EMPTY_ID; self.width as usize]; self.height as usize];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                res[y as usize][x as usize] = self.get_particle_id((x, y));
-            }
+    fn sync_cell_ids(&mut self, position: Position) {
+        if let Some(particle) = self.cells.get(position) {
+            self.cell_types[position] = particle.get_id();
+            self.cell_behaviors[position] = particle.get_behaviors_ids();
         }
-        res
     }
 
-    fn build_cell_behaviors(&self) -> Vec<Vec<BehaviorId>> {
-        let mut res = vec![vec![0; self.width as usize]; self.height as usize];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                res[y as usize][x as usize] = self.get((x, y)).get_behaviors_ids();
-            }
+    fn refresh_cell_ids(&mut self) {
+        let cells = self.cells.as_slice();
+        for (cell_type, particle) in self.cell_types.as_mut_slice().iter_mut().zip(cells) {
+            *cell_type = particle.get_id();
+        }
+        for (cell_behaviors, particle) in self.cell_behaviors.as_mut_slice().iter_mut().zip(cells) {
+            *cell_behaviors = particle.get_behaviors_ids();
         }
-        res
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,81 @@
+use crate::sandsim::grid::{Grid, Position};
+use crate::sandsim::particle::*;
+
+/// Temperature changes smaller than this do not keep a cell awake (°C)
+pub const HEAT_EPSILON: f64 = 0.01;
+
+// Largest fraction of a temperature difference exchanged by two cells in a tick, for the diffusion to stay stable
+const MAX_EXCHANGE: f64 = 0.2;
+
+impl Grid {
+    /// Exchanges heat between neighbouring cells of the awake chunks, and cools particles down to `AMBIENT_TEMPERATURE`.
+    /// Two neighbours exchange `conductivity * dt * (difference of temperature)` units of heat per tick, `conductivity` being the
+    /// lowest of the two materials, and each temperature changes by the heat divided by the heat capacity of its material.
+    /// Cells whose temperature changes are kept awake.
+    pub(crate) fn diffuse_heat(&mut self, dt: f64) {
+        for chunk_y in 0..self.chunks.rows() {
+            for chunk_x in 0..self.chunks.columns() {
+                let Some(rect) = self.chunks.update_rect((chunk_x, chunk_y)) else {
+                    continue;
+                };
+                for position in rect.positions() {
+                    self.cool_down(position, dt);
+                    // Each pair of neighbours is visited once: from its left or top cell,
+                    // or from the updated cell when the other one is asleep
+                    for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                        let neighbour = (position.0 + dx, position.1 + dy);
+                        let first_of_pair = dx + dy > 0 || !self.chunks.is_updated(neighbour);
+                        if first_of_pair && self.cells.in_bounds(neighbour) {
+                            self.exchange_heat(position, neighbour, dt);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cool_down(&mut self, position: Position, dt: f64) {
+        let cooling_rate = self.thermal_properties(position).2;
+        let temperature = self.cells[position].get_temperature();
+        if cooling_rate <= 0. || temperature == AMBIENT_TEMPERATURE {
+            return;
+        }
+
+        let change = (AMBIENT_TEMPERATURE - temperature) * (cooling_rate * dt).min(1.);
+        let cooled = if change.abs() < HEAT_EPSILON { AMBIENT_TEMPERATURE } else { temperature + change };
+        self.cells[position].set_temperature(cooled);
+        if change.abs() >= HEAT_EPSILON {
+            self.chunks.keep_awake(position);
+        }
+    }
+
+    fn exchange_heat(&mut self, a: Position, b: Position, dt: f64) {
+        let difference = self.cells[b].get_temperature() - self.cells[a].get_temperature();
+        if difference.abs() < HEAT_EPSILON {
+            return;
+        }
+
+        let (conductivity_a, capacity_a, _) = self.thermal_properties(a);
+        let (conductivity_b, capacity_b, _) = self.thermal_properties(b);
+        let smallest_capacity = capacity_a.min(capacity_b);
+        let heat = difference * smallest_capacity * (conductivity_a.min(conductivity_b) * dt / smallest_capacity).min(MAX_EXCHANGE);
+        if heat == 0. {
+            return;
+        }
+
+        let temperature_a = self.cells[a].get_temperature() + heat / capacity_a;
+        let temperature_b = self.cells[b].get_temperature() - heat / capacity_b;
+        self.cells[a].set_temperature(temperature_a);
+        self.cells[b].set_temperature(temperature_b);
+        if (heat / smallest_capacity).abs() >= HEAT_EPSILON {
+            self.chunks.keep_awake(a);
+            self.chunks.keep_awake(b);
+        }
+    }
+
+    // Conductivity, heat capacity and cooling rate of the material at the given position
+    fn thermal_properties(&self, position: Position) -> (f64, f64, f64) {
+        let material = self.materials.get(self.cells[position].get_id()).unwrap();
+        (material.thermal_conductivity, material.heat_capacity, material.cooling_rate)
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use crate::sandsim::grid::Grid;
+use crate::sandsim::level::*;
+use crate::sandsim::particle::*;
+
+impl Grid {
+    /// Replaces the content of the grid by the PNG image at `path`, each pixel becoming a particle of the material
+    /// the palette gives for its color
+    pub fn load_level(&mut self, path: impl AsRef<Path>, palette: &Palette, fit: FitMode) -> Result<(), LevelError> {
+        let image = LevelImage::load(path)?;
+        self.fill_from_image(&image, palette, fit);
+        Ok(())
+    }
+
+    pub fn fill_from_image(&mut self, image: &LevelImage, palette: &Palette, fit: FitMode) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = match fit {
+                    FitMode::Resize => Some((
+                        (x as u64 * image.width as u64 / self.width as u64) as u32,
+                        (y as u64 * image.height as u64 / self.height as u64) as u32)),
+                    FitMode::Crop => Some((x as u32, y as u32)).filter(|(x, y)| *x < image.width && *y < image.height),
+                };
+                let material = pixel.map_or(EMPTY_ID, |(px, py)| palette.material_for(image.get(px, py)));
+                self.spawn((x, y), material);
+            }
+        }
+    }
+}
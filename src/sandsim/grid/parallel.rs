@@ -0,0 +1,94 @@
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::sandsim::chunks::{DirtyRect, CHUNK_SIZE};
+use crate::sandsim::grid::{Grid, Position, SimRng};
+use crate::sandsim::region::Region;
+
+/// How far from its chunk a particle can reach during a parallel update.
+/// Chunks updated at the same time are two chunks apart, so their reaches never overlap.
+pub const PARALLEL_REACH: i32 = CHUNK_SIZE / 2;
+
+// Order in which the four sets of non-adjacent chunks are updated
+const PASSES: [Position; 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+impl Grid {
+    /// Updates the awake chunks on several threads, in four passes following a checkerboard pattern.
+    /// Chunks updated during the same pass are never adjacent, so a particle moving across the border of its chunk
+    /// only touches cells that no other thread is working on.
+    /// During a parallel update, a particle cannot reach cells further than `PARALLEL_REACH` from its chunk,
+    /// and each chunk uses its own random number generator, derived from the grid one.
+    /// Explosives, whose blasts reach further, are updated once the chunks are done, one after the other.
+    pub fn update_parallel(&mut self, dt: f64) {
+        self.tick += 1;
+        self.chunks.begin_tick();
+        let tick_seed: u64 = self.rng.gen();
+        let tick = self.tick;
+        let columns = self.chunks.columns();
+        let materials = &self.materials;
+        let mut explosives = vec![];
+
+        for (pass_x, pass_y) in PASSES {
+            // Chunks of this pass with something to update, along with the cells they can reach
+            let mut tiles = vec![];
+            for chunk_y in (pass_y..self.chunks.rows()).step_by(2) {
+                for chunk_x in (pass_x..columns).step_by(2) {
+                    if let Some(update_rect) = self.chunks.update_rect((chunk_x, chunk_y)) {
+                        let bounds = self.chunks.chunk_bounds((chunk_x, chunk_y));
+                        let reach = DirtyRect::new(
+                            (bounds.min_x - PARALLEL_REACH, bounds.min_y - PARALLEL_REACH),
+                            (bounds.max_x + PARALLEL_REACH, bounds.max_y + PARALLEL_REACH));
+                        tiles.push(((chunk_x, chunk_y), update_rect, reach));
+                    }
+                }
+            }
+            if tiles.is_empty() {
+                continue;
+            }
+
+            let reaches: Vec<DirtyRect> = tiles.iter().map(|(_, _, reach)| *reach).collect();
+            let cells = self.cells.split_windows_mut(&reaches);
+            let cell_types = self.cell_types.split_windows_mut(&reaches);
+            let cell_behaviors = self.cell_behaviors.split_windows_mut(&reaches);
+
+            let mut regions: Vec<_> = tiles.iter()
+                .zip(cells.into_iter().zip(cell_types).zip(cell_behaviors))
+                .map(|(&(chunk, update_rect, _), ((cells, cell_types), cell_behaviors))| {
+                    let mut region = Region::new(cells, cell_types, cell_behaviors, materials, tick);
+                    region.defer_explosives();
+                    (chunk, update_rect, region)
+                })
+                .collect();
+
+            regions.par_iter_mut().for_each(|((chunk_x, chunk_y), update_rect, region)| {
+                let chunk_index = (*chunk_y * columns + *chunk_x) as u64;
+                let mut rng = SimRng::seed_from_u64(tick_seed ^ chunk_index.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+                for y in (update_rect.min_y..=update_rect.max_y).rev() {
+                    let (mut x, step) = if rng.gen::<f32>() < 0.5 { (update_rect.min_x, 1) } else { (update_rect.max_x, -1) };
+                    while x >= update_rect.min_x && x <= update_rect.max_x {
+                        region.update_cell((x, y), dt, &mut rng);
+                        x += step;
+                    }
+                }
+            });
+
+            // Chunk changes are applied in a fixed order, so that the result does not depend on the threads
+            for (_, _, region) in regions.iter_mut() {
+                region.apply_events(&mut self.chunks);
+                explosives.extend(region.take_deferred());
+            }
+        }
+
+        let mut region = Region::new(self.cells.window_mut(), self.cell_types.window_mut(), self.cell_behaviors.window_mut(), &self.materials, tick);
+        for position in explosives {
+            region.update_cell(position, dt, &mut self.rng);
+            region.apply_events(&mut self.chunks);
+        }
+
+        self.react(dt);
+        self.collapse_structures(dt);
+        self.diffuse_heat(dt);
+        self.spread_pressure();
+    }
+}
@@ -0,0 +1,65 @@
+use crate::sandsim::behaviors::GAS_ID;
+use crate::sandsim::grid::{Grid, Position};
+use crate::sandsim::particle::*;
+
+/// Pressure changes smaller than this do not keep a cell awake
+pub const PRESSURE_EPSILON: f64 = 0.01;
+
+impl Grid {
+    /// Spreads the gases of the awake chunks. Neighbouring particles with the `Gas` behavior even out their pressure,
+    /// and a particle with at least twice the `AMBIENT_PRESSURE` shares it with a new particle of its material in an
+    /// empty neighbour, so that gases expand to fill the space they are in. Cells whose pressure changes are kept awake.
+    pub(crate) fn spread_pressure(&mut self) {
+        for chunk_y in 0..self.chunks.rows() {
+            for chunk_x in 0..self.chunks.columns() {
+                let Some(rect) = self.chunks.update_rect((chunk_x, chunk_y)) else {
+                    continue;
+                };
+                for position in rect.positions() {
+                    if !self.cell_behaviors[position].contains(GAS_ID) {
+                        continue;
+                    }
+                    for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                        let neighbour = (position.0 + dx, position.1 + dy);
+                        if !self.cells.in_bounds(neighbour) {
+                            continue;
+                        }
+                        // Pairs of gases are visited once, as in `diffuse_heat`
+                        if self.cell_types[neighbour] == EMPTY_ID {
+                            self.expand(position, neighbour);
+                        } else if self.cell_behaviors[neighbour].contains(GAS_ID) && (dx + dy > 0 || !self.chunks.is_updated(neighbour)) {
+                            self.balance_pressure(position, neighbour);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn expand(&mut self, from: Position, to: Position) {
+        let pressure = self.cells[from].get_pressure();
+        if pressure < 2. * AMBIENT_PRESSURE {
+            return;
+        }
+
+        let mut particle = self.materials.create(self.cells[from].get_id(), to, &mut self.rng);
+        particle.set_pressure(pressure / 2.);
+        particle.set_temperature(self.cells[from].get_temperature());
+        self.cells[from].set_pressure(pressure / 2.);
+        self.set(to, particle);
+        self.chunks.keep_awake(from);
+    }
+
+    fn balance_pressure(&mut self, a: Position, b: Position) {
+        let (pressure_a, pressure_b) = (self.cells[a].get_pressure(), self.cells[b].get_pressure());
+        if (pressure_a - pressure_b).abs() < PRESSURE_EPSILON {
+            return;
+        }
+
+        let pressure = (pressure_a + pressure_b) / 2.;
+        self.cells[a].set_pressure(pressure);
+        self.cells[b].set_pressure(pressure);
+        self.chunks.keep_awake(a);
+        self.chunks.keep_awake(b);
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use crate::sandsim::grid::Grid;
+
+impl Grid {
+    /// Applies the reactions of the `ReactionTable` between neighbouring cells of the awake chunks. Each pair of neighbours
+    /// that can react does so with the probability of its reaction, and the reactants are replaced by the products.
+    /// Cells next to something they can react with are kept awake.
+    pub(crate) fn react(&mut self, dt: f64) {
+        let materials = Arc::clone(&self.materials);
+        let reactions = materials.reactions();
+        for chunk_y in 0..self.chunks.rows() {
+            for chunk_x in 0..self.chunks.columns() {
+                let Some(rect) = self.chunks.update_rect((chunk_x, chunk_y)) else {
+                    continue;
+                };
+                for position in rect.positions() {
+                    let id = self.cell_types[position];
+                    if !reactions.involves(id) {
+                        continue;
+                    }
+                    for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                        let neighbour = (position.0 + dx, position.1 + dy);
+                        if !self.cells.in_bounds(neighbour) {
+                            continue;
+                        }
+                        // Pairs are visited once, as in `diffuse_heat`, unless the neighbour never visits them itself
+                        let other = self.cell_types[neighbour];
+                        let first_of_pair = dx + dy > 0 || !self.chunks.is_updated(neighbour);
+                        if !first_of_pair || reactions.find(id, other).is_none() {
+                            continue;
+                        }
+
+                        self.chunks.keep_awake(position);
+                        if let Some((product, other_product)) = reactions.react(id, other, dt, &mut self.rng) {
+                            if product != id {
+                                self.spawn(position, product);
+                            }
+                            if other_product != other {
+                                self.spawn(neighbour, other_product);
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
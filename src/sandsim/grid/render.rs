@@ -0,0 +1,23 @@
+use std::path::Path;
+
+use crate::sandsim::grid::Grid;
+use crate::sandsim::render::{ImageError, RgbaImage};
+
+impl Grid {
+    /// Renders the cells to an image, each cell being a `scale` x `scale` square. Panics if `scale` is 0.
+    pub fn render(&self, scale: u32) -> RgbaImage {
+        assert!(scale > 0, "The scale of a render must be at least 1");
+        let mut image = RgbaImage::new(self.width as u32 * scale, self.height as u32 * scale);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.fill_square(x as u32 * scale, y as u32 * scale, scale, self.get((x, y)).get_color());
+            }
+        }
+        image
+    }
+
+    /// Writes a PNG screenshot of the grid, see `render`
+    pub fn save_screenshot(&self, path: impl AsRef<Path>, scale: u32) -> Result<(), ImageError> {
+        self.render(scale).save_png(path)
+    }
+}
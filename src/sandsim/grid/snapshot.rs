@@ -0,0 +1,180 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::SeedableRng;
+
+use crate::sandsim::behaviors::BehaviorRegistry;
+use crate::sandsim::chunks::DirtyRect;
+use crate::sandsim::grid::{Grid, SimRng};
+use crate::sandsim::grid::structures::FallingBody;
+use crate::sandsim::material::MaterialRegistry;
+use crate::sandsim::particle::*;
+use crate::sandsim::snapshot::*;
+
+// Smallest size of a cell in a snapshot: material id, color, temperature, pressure, velocity and number of behaviors
+const MIN_CELL_SIZE: usize = 38;
+
+/// Snapshot layout, all values in little endian:
+/// - `SNAPSHOT_MAGIC`, `SNAPSHOT_VERSION` (u16)
+/// - width, height (i32), seed, tick (u64), random number generator seed ([u8; 32]), stream (u64) and word position (u128)
+/// - material table: count (u16), then the id (u8) and name of each material
+/// - behavior table: count (u16), then the name of each behavior, indexed by id
+/// - next update rectangle of each chunk: presence (u8) and bounds (4 x i32)
+/// - cells, row by row: material id (u8), then the particle state (see `Particle::save_state`)
+/// - falling structures: count (u32), then the velocity and offset (f64), and the number of cells (u32) and their
+///   positions (2 x i32) of each structure
+///
+/// Names are a length (u16) followed by UTF-8 bytes.
+impl Grid {
+    /// Writes the whole grid to a file. Loading it gives back the exact same grid, which then evolves the same way.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        std::fs::write(path, self.to_snapshot())?;
+        Ok(())
+    }
+
+    /// Reads a grid written by `save`. The materials of the snapshot are looked up by name in `materials`.
+    pub fn load(path: impl AsRef<Path>, materials: Arc<MaterialRegistry>) -> Result<Grid, SnapshotError> {
+        Self::from_snapshot(&std::fs::read(path)?, materials)
+    }
+
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        writer.write_bytes(&SNAPSHOT_MAGIC);
+        writer.write_u16(SNAPSHOT_VERSION);
+
+        writer.write_i32(self.width);
+        writer.write_i32(self.height);
+        writer.write_u64(self.seed);
+        writer.write_u64(self.tick);
+        writer.write_bytes(&self.rng.get_seed());
+        writer.write_u64(self.rng.get_stream());
+        writer.write_u128(self.rng.get_word_pos());
+
+        let materials: Vec<_> = self.materials.iter().collect();
+        writer.write_u16(materials.len() as u16);
+        for material in materials {
+            writer.write_u8(material.id);
+            writer.write_str(&material.name);
+        }
+
+        let behaviors = BehaviorRegistry::global().names();
+        writer.write_u16(behaviors.len() as u16);
+        for name in &behaviors {
+            writer.write_str(name);
+        }
+
+        for rect in self.chunks.next_update_rects() {
+            writer.write_bool(rect.is_some());
+            let rect = rect.unwrap_or(DirtyRect::new((0, 0), (0, 0)));
+            for value in [rect.min_x, rect.min_y, rect.max_x, rect.max_y] {
+                writer.write_i32(value);
+            }
+        }
+
+        for particle in self.cells.iter() {
+            writer.write_u8(particle.get_id());
+            particle.save_state(&mut writer);
+        }
+
+        writer.write_u32(self.bodies.len() as u32);
+        for body in &self.bodies {
+            writer.write_f64(body.velocity);
+            writer.write_f64(body.offset);
+            writer.write_u32(body.cells.len() as u32);
+            for (position, _) in &body.cells {
+                writer.write_i32(position.0);
+                writer.write_i32(position.1);
+            }
+        }
+
+        writer.into_bytes()
+    }
+
+    pub fn from_snapshot(bytes: &[u8], materials: Arc<MaterialRegistry>) -> Result<Grid, SnapshotError> {
+        let mut reader = StateReader::new(bytes);
+        if reader.read_bytes(SNAPSHOT_MAGIC.len()).ok() != Some(&SNAPSHOT_MAGIC[..]) {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let version = reader.read_u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion { found: version, supported: SNAPSHOT_VERSION });
+        }
+
+        let width = reader.read_i32()?;
+        let height = reader.read_i32()?;
+        if width <= 0 || height <= 0 || (width as usize).saturating_mul(height as usize) > bytes.len() / MIN_CELL_SIZE {
+            return Err(SnapshotError::Invalid(format!("the grid size {}x{} does not match the file size", width, height)));
+        }
+        let seed = reader.read_u64()?;
+        let tick = reader.read_u64()?;
+        let mut rng = SimRng::from_seed(reader.read_bytes(32)?.try_into().unwrap());
+        rng.set_stream(reader.read_u64()?);
+        rng.set_word_pos(reader.read_u128()?);
+
+        // Ids of the snapshot to current ids
+        let mut material_ids = vec![None; ParticleId::MAX as usize + 1];
+        for _ in 0..reader.read_u16()? {
+            let id = reader.read_u8()?;
+            let name = reader.read_str()?;
+            let current_id = materials.id_of(&name)
+                .ok_or_else(|| SnapshotError::Invalid(format!("unknown material \"{}\"", name)))?;
+            material_ids[id as usize] = Some(current_id);
+        }
+        let behavior_ids = (0..reader.read_u16()?)
+            .map(|_| reader.read_str().map(|name| BehaviorRegistry::global().id_of(&name)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut grid = Grid::with_materials(width, height, seed, materials);
+        grid.tick = tick;
+        grid.rng = rng;
+
+        let mut next_update_rects = vec![];
+        for index in 0..grid.chunks.columns() * grid.chunks.rows() {
+            let awake = reader.read_bool()?;
+            let (min_x, min_y, max_x, max_y) = (reader.read_i32()?, reader.read_i32()?, reader.read_i32()?, reader.read_i32()?);
+            let rect = DirtyRect::new((min_x, min_y), (max_x, max_y));
+            let chunk = (index % grid.chunks.columns(), index / grid.chunks.columns());
+            if awake && grid.chunks.chunk_bounds(chunk).intersection(&rect) != Some(rect) {
+                return Err(SnapshotError::Invalid(format!("the update rectangle of chunk ({}, {}) is not within the chunk", chunk.0, chunk.1)));
+            }
+            next_update_rects.push(Some(rect).filter(|_| awake));
+        }
+
+        // Random choices made when creating the particles are overwritten by their saved state
+        let mut scratch_rng = SimRng::seed_from_u64(0);
+        for y in 0..height {
+            for x in 0..width {
+                let id = reader.read_u8()?;
+                let material = material_ids[id as usize]
+                    .ok_or_else(|| SnapshotError::Invalid(format!("the material id {} is not in the material table", id)))?;
+                let mut particle = grid.materials.create(material, (x, y), &mut scratch_rng);
+                particle.load_state(&mut reader, &behavior_ids)
+                    .map_err(|error| match error {
+                        SnapshotError::Invalid(message) => SnapshotError::Invalid(format!("cell ({}, {}): {}", x, y, message)),
+                        error => error,
+                    })?;
+                grid.cells[(x, y)] = particle;
+            }
+        }
+        grid.refresh_cell_ids();
+
+        for _ in 0..reader.read_u32()? {
+            let (velocity, offset) = (reader.read_f64()?, reader.read_f64()?);
+            let mut cells = vec![];
+            for _ in 0..reader.read_u32()? {
+                let position = (reader.read_i32()?, reader.read_i32()?);
+                if !grid.cells.in_bounds(position) {
+                    return Err(SnapshotError::Invalid(format!("falling cell ({}, {}) out of the grid", position.0, position.1)));
+                }
+                cells.push((position, grid.cell_types[position]));
+            }
+            grid.bodies.push(FallingBody { cells, velocity, offset });
+        }
+        if !reader.is_empty() {
+            return Err(SnapshotError::Invalid("unexpected data after the falling structures".to_string()));
+        }
+
+        grid.chunks.restore_next_update_rects(&next_update_rects);
+        Ok(grid)
+    }
+}
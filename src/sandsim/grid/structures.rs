@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::sandsim::grid::{Grid, Position};
+use crate::sandsim::material::Phase;
+use crate::sandsim::particle::*;
+
+// Falling structures accelerate and are limited like sand (cells/s², cells/s)
+const ACCELERATION: f64 = 360.;
+const MAX_VELOCITY: f64 = 480.;
+
+/// A structure of static particles falling as one piece, see `Grid::collapse_structures`
+pub(crate) struct FallingBody {
+    pub cells: Vec<(Position, ParticleId)>, // From the bottom up, so that each cell moves into room made by the ones below
+    pub velocity: f64,
+    pub offset: f64, // Distance fallen within the current cell
+}
+
+impl Grid {
+    /// Makes the structures that lost their support fall. Structures are groups of static particles touching each other,
+    /// diagonally included, checked when a cell of an awake chunk changes next to them. They hold if one of their
+    /// particles is anchored, is on the bottom row, or rests, straight or diagonally, on a solid or powder that is not
+    /// part of the structure, or on a fluid denser than the structure.
+    /// Falling structures move as one piece through empty cells and lighter fluids, and land intact, except the particles
+    /// whose material shatters at a lower speed than the one of the impact.
+    pub(crate) fn collapse_structures(&mut self, dt: f64) {
+        self.find_falling_structures();
+
+        let bodies = std::mem::take(&mut self.bodies);
+        for mut body in bodies {
+            if self.fall(&mut body, dt) {
+                self.bodies.push(body);
+            }
+        }
+    }
+
+    fn find_falling_structures(&mut self) {
+        let materials = Arc::clone(&self.materials);
+        let is_static = |id: ParticleId| materials.get(id).unwrap().phase == Phase::Static;
+
+        // Falling cells are not looked at again, and each structure is visited once
+        let stamp = self.tick;
+        for body in &self.bodies {
+            for (position, _) in &body.cells {
+                self.structure_visits[*position] = stamp;
+            }
+        }
+
+        // Only the structures that can have lost their support are checked: new static particles, the neighbours of
+        // removed ones, and the static particles resting on a cell that changed
+        let mut seeds = vec![];
+        for chunk_y in 0..self.chunks.rows() {
+            for chunk_x in 0..self.chunks.columns() {
+                let Some(rect) = self.chunks.update_rect((chunk_x, chunk_y)) else {
+                    continue;
+                };
+                for (x, y) in rect.positions() {
+                    let (id, previous) = (self.cell_types[(x, y)], self.structure_types[(x, y)]);
+                    if id == previous {
+                        continue;
+                    }
+                    self.structure_types[(x, y)] = id;
+                    if is_static(id) {
+                        seeds.push((x, y));
+                    }
+                    let min_dy = if is_static(previous) { -1 } else { 1 };
+                    seeds.extend((min_dy..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y - dy))));
+                }
+            }
+        }
+
+        for position in seeds {
+            if !self.cells.in_bounds(position) || self.structure_visits[position] == stamp || !is_static(self.cell_types[position]) {
+                continue;
+            }
+
+            let mut structure = vec![position];
+            self.structure_visits[position] = stamp;
+            let mut i = 0;
+            while i < structure.len() {
+                let (x, y) = structure[i];
+                i += 1;
+                // Diagonals included, which is how plants grow
+                for neighbour in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy))) {
+                    if self.cells.in_bounds(neighbour) && self.structure_visits[neighbour] != stamp && is_static(self.cell_types[neighbour]) {
+                        self.structure_visits[neighbour] = stamp;
+                        structure.push(neighbour);
+                    }
+                }
+            }
+
+            let density = self.density(structure.iter().map(|&position| self.cell_types[position]));
+            let supported = structure.iter().any(|&position| self.supports(position, density, &is_static));
+            if !supported {
+                structure.sort_by_key(|&(x, y)| (-y, x));
+                let cells = structure.into_iter().map(|position| (position, self.cell_types[position])).collect();
+                self.bodies.push(FallingBody { cells, velocity: 0., offset: 0. });
+            }
+        }
+    }
+
+    /// Whether the particle at `position`, part of a structure of the given density, holds it up by itself or through
+    /// one of the three cells below it
+    fn supports(&self, (x, y): Position, density: f64, is_static: &impl Fn(ParticleId) -> bool) -> bool {
+        if self.materials.get(self.cell_types[(x, y)]).unwrap().anchored || y == self.height - 1 {
+            return true;
+        }
+        (-1..=1).map(|dx| (x + dx, y + 1))
+            .filter(|below| self.cells.in_bounds(*below))
+            .any(|below| {
+                let id = self.cell_types[below];
+                id != EMPTY_ID && !is_static(id) && !self.sinks_into(density, id)
+            })
+    }
+
+    /// Whether a structure of the given density can move into a cell of the given material
+    fn sinks_into(&self, density: f64, id: ParticleId) -> bool {
+        let material = self.materials.get(id).unwrap();
+        id == EMPTY_ID || (material.phase.is_fluid() && density > material.density)
+    }
+
+    /// Average density of the particles of a structure, which floats on denser liquids
+    fn density(&self, ids: impl ExactSizeIterator<Item = ParticleId>) -> f64 {
+        let count = ids.len() as f64;
+        ids.map(|id| self.materials.get(id).unwrap().density).sum::<f64>() / count
+    }
+
+    /// Moves a falling structure, and returns whether it is still falling
+    fn fall(&mut self, body: &mut FallingBody, dt: f64) -> bool {
+        // Burnt or dissolved on the way
+        body.cells.retain(|&(position, id)| self.cell_types[position] == id);
+        if body.cells.is_empty() {
+            return false;
+        }
+
+        body.velocity = (body.velocity + ACCELERATION * dt).min(MAX_VELOCITY);
+        body.offset += body.velocity * dt;
+        while body.offset >= 1. {
+            body.offset -= 1.;
+            if !self.can_fall(body) {
+                self.land(body);
+                return false;
+            }
+            for (position, _) in body.cells.iter_mut() {
+                let below = (position.0, position.1 + 1);
+                self.swap(*position, below);
+                *position = below;
+            }
+        }
+        true
+    }
+
+    fn can_fall(&self, body: &FallingBody) -> bool {
+        let density = self.density(body.cells.iter().map(|&(_, id)| id));
+        let positions: HashSet<Position> = body.cells.iter().map(|&(position, _)| position).collect();
+        body.cells.iter().all(|&((x, y), _)| {
+            let below = (x, y + 1);
+            self.cells.in_bounds(below) && (positions.contains(&below) || self.sinks_into(density, self.cell_types[below]))
+        })
+    }
+
+    fn land(&mut self, body: &FallingBody) {
+        for &(position, id) in &body.cells {
+            let shatter = self.materials.get(id).unwrap().shatter.as_ref()
+                .filter(|shatter| body.velocity >= shatter.speed)
+                .map(|shatter| self.materials.id_of(&shatter.into).unwrap());
+            if let Some(material) = shatter {
+                self.spawn(position, material);
+            }
+        }
+    }
+}
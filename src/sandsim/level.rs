@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::color::Color;
+use crate::sandsim::material::MaterialRegistry;
+use crate::sandsim::particle::*;
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Decode(png::DecodingError),
+    Palette(String),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "Could not read the level: {}", error),
+            LevelError::Decode(error) => write!(f, "Could not decode the level image: {}", error),
+            LevelError::Palette(message) => write!(f, "Invalid palette: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(error: std::io::Error) -> Self {
+        LevelError::Io(error)
+    }
+}
+
+impl From<png::DecodingError> for LevelError {
+    fn from(error: png::DecodingError) -> Self {
+        LevelError::Decode(error)
+    }
+}
+
+/// An RGBA image, decoded from a PNG file
+pub struct LevelImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>, // Row-major
+}
+
+impl LevelImage {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        Self::decode(BufReader::new(File::open(path)?))
+    }
+
+    /// Decodes a PNG image of any color type and bit depth
+    pub fn decode(reader: impl Read) -> Result<Self, LevelError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let bytes = &buffer[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| Color::rgba(p[0], p[1], p[2], p[3])).collect(),
+            png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| Color::rgb(p[0], p[1], p[2])).collect(),
+            png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|p| Color::rgba(p[0], p[0], p[0], p[1])).collect(),
+            png::ColorType::Grayscale => bytes.iter().map(|v| Color::rgb(*v, *v, *v)).collect(),
+            png::ColorType::Indexed => unreachable!("Indexed images are expanded to RGB by the decoder"),
+        };
+
+        Ok(Self { width: info.width, height: info.height, pixels })
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+/// How an image that does not have the size of the grid is fitted to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitMode {
+    /// Stretch the image to the size of the grid, using the nearest pixel
+    Resize,
+    /// Keep one pixel per cell, from the top left corner. Cells outside of the image are left empty.
+    Crop,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PaletteFile {
+    entry: Vec<PaletteEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PaletteEntry {
+    color: Color,
+    material: String,
+}
+
+/// `Palette` maps pixel colors to materials.
+/// Colors that are not in the palette get the material of the closest color, transparent pixels are empty.
+pub struct Palette {
+    entries: Vec<(Color, ParticleId)>,
+    exact: HashMap<Color, ParticleId>,
+}
+
+impl Palette {
+    /// Pixels with an alpha below this are empty
+    pub const ALPHA_THRESHOLD: u8 = 128;
+
+    pub fn new(entries: Vec<(Color, ParticleId)>) -> Self {
+        let exact = entries.iter().map(|(color, id)| (Color { a: 255, ..*color }, *id)).collect();
+        Self { entries, exact }
+    }
+
+    /// Maps the base color of each material to the material
+    pub fn from_materials(materials: &MaterialRegistry) -> Self {
+        Self::new(materials.iter().map(|material| (material.color, material.id)).collect())
+    }
+
+    /// Reads a palette file: a list of `[[entry]]` tables with a `color` ([r, g, b]) and a `material` name
+    pub fn load(path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<Self, LevelError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?, materials)
+    }
+
+    pub fn from_toml_str(content: &str, materials: &MaterialRegistry) -> Result<Self, LevelError> {
+        let file: PaletteFile = toml::from_str(content).map_err(|error| LevelError::Palette(error.to_string()))?;
+        let entries = file.entry.into_iter()
+            .map(|entry| match materials.id_of(&entry.material) {
+                Some(id) => Ok((entry.color, id)),
+                None => Err(LevelError::Palette(format!("unknown material \"{}\"", entry.material))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if entries.is_empty() {
+            return Err(LevelError::Palette("the palette has no entry".to_string()));
+        }
+        Ok(Self::new(entries))
+    }
+
+    pub fn material_for(&self, color: Color) -> ParticleId {
+        if color.a < Self::ALPHA_THRESHOLD {
+            return EMPTY_ID;
+        }
+        if let Some(id) = self.exact.get(&Color { a: 255, ..color }) {
+            return *id;
+        }
+
+        let distance = |other: &Color| {
+            let (dr, dg, db) = (color.r as i32 - other.r as i32, color.g as i32 - other.g as i32, color.b as i32 - other.b as i32);
+            dr * dr + dg * dg + db * db
+        };
+        self.entries.iter()
+            .min_by_key(|(other, _)| distance(other))
+            .map_or(EMPTY_ID, |(_, id)| *id)
+    }
+}
@@ -0,0 +1,625 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::color::{self, Color};
+use crate::sandsim::behaviors::*;
+use crate::sandsim::chunks::WAKE_RADIUS;
+use crate::sandsim::grid::{Position, SimRng};
+use crate::sandsim::particle::*;
+use crate::sandsim::reaction::{ReactionDef, ReactionTable};
+
+/// Definitions of the built-in materials, see `materials.toml` at the root of the repository
+pub const BUILTIN_MATERIALS: &str = include_str!("../../materials.toml");
+
+/// Largest radius of an `Explosive`, whose blast reaches cells up to twice as far.
+/// Explosives are updated on the whole grid even during a parallel update (see `Grid::update_parallel`).
+pub const MAX_BLAST_RADIUS: i32 = 12;
+
+#[derive(Debug)]
+pub enum MaterialError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialError::Io(error) => write!(f, "Could not read the material file: {}", error),
+            MaterialError::Parse(error) => write!(f, "Could not parse the material file: {}", error),
+            MaterialError::Invalid(message) => write!(f, "Invalid material definition: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+impl From<std::io::Error> for MaterialError {
+    fn from(error: std::io::Error) -> Self {
+        MaterialError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for MaterialError {
+    fn from(error: toml::de::Error) -> Self {
+        MaterialError::Parse(error)
+    }
+}
+
+/// A value that is either fixed, or picked uniformly in `[min, max]` for each particle
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ValueRange {
+    Value(f64),
+    Range([f64; 2]),
+}
+
+impl ValueRange {
+    pub fn min(&self) -> f64 {
+        match self {
+            ValueRange::Value(value) => *value,
+            ValueRange::Range([min, _]) => *min,
+        }
+    }
+
+    pub fn max(&self) -> f64 {
+        match self {
+            ValueRange::Value(value) => *value,
+            ValueRange::Range([_, max]) => *max,
+        }
+    }
+
+    pub fn pick(&self, rng: &mut SimRng) -> f64 {
+        match self {
+            ValueRange::Range([min, max]) if min < max => rng.gen_range(*min..=*max),
+            _ => self.min(),
+        }
+    }
+}
+
+/// Particle spawned by a `LimitedLife` behavior when it dies
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnDef {
+    pub material: String,
+    pub probability: f64,
+    #[serde(default)]
+    pub distance: (i32, i32),
+}
+
+/// A behavior and its parameters, as declared in a material file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum BehaviorDef {
+    MoveDown { max_velocity: f64, acceleration: f64 },
+    AirLike,
+    LimitedLife {
+        lifetime: ValueRange,
+        #[serde(default)]
+        spawn: Option<SpawnDef>,
+    },
+    AnimatedColor {
+        colors: Vec<Color>,
+        frequency: ValueRange,
+        #[serde(default)]
+        color_variance: i8,
+    },
+    Flammable {
+        #[serde(default)]
+        ignition_rate: f64,
+        #[serde(default)]
+        ignition_radius: i32,
+        #[serde(default)]
+        ignition_temperature: Option<f64>, // Replaces the igniter counting when set
+        #[serde(default = "default_burns_into")]
+        burns_into: String,
+    },
+    DieWhenCrushed { crushing_probability: f64 },
+    Igniter,
+    SidewaysMotionFallback,
+    CurrentMotion { swap_probability_per_sec: f64 },
+    HeatSource { temperature: f64 },
+    Gas { diffusion_rate: f64 },
+    Explosive {
+        radius: i32,
+        strength: f64,
+        impulse: f64,
+        #[serde(default)]
+        ignition_temperature: Option<f64>,
+        #[serde(default = "default_burns_into")]
+        fire: String,
+        #[serde(default = "default_smoke")]
+        smoke: String,
+    },
+    Liquid {
+        dispersion_rate: i32,
+        #[serde(default)]
+        viscosity: f64,
+    },
+    Corrosive {
+        rate: f64,
+        strength: f64,
+        #[serde(default)]
+        gas: Option<String>,
+        #[serde(default)]
+        gas_probability: f64,
+    },
+    Emitter { material: String, rate: f64 },
+    Grows {
+        rate: f64,
+        max_size: u32,
+        stem: String,
+        #[serde(default)]
+        sway_probability: f64,
+        #[serde(default)]
+        branch_probability: f64,
+        #[serde(default = "default_water")]
+        water: String,
+    },
+}
+
+fn default_burns_into() -> String {
+    "fire".to_string()
+}
+
+fn default_smoke() -> String {
+    "smoke".to_string()
+}
+
+fn default_water() -> String {
+    "water".to_string()
+}
+
+impl BehaviorDef {
+    /// Creates the behavior for a new particle
+    pub fn build(&self, position: Position, materials: &MaterialRegistry, rng: &mut SimRng) -> Box<dyn Behavior> {
+        match self {
+            BehaviorDef::MoveDown { max_velocity, acceleration } => MoveDown::boxed(position, *max_velocity, *acceleration),
+            BehaviorDef::AirLike => AirLike::boxed(),
+            BehaviorDef::LimitedLife { lifetime, spawn: None } => LimitedLife::boxed(lifetime.pick(rng)),
+            BehaviorDef::LimitedLife { lifetime, spawn: Some(spawn) } => LimitedLife::boxed_with_spawn(
+                lifetime.pick(rng),
+                spawn.probability,
+                materials.expect_id(&spawn.material),
+                spawn.distance),
+            BehaviorDef::AnimatedColor { colors, frequency, color_variance } => AnimatedColor::boxed(
+                colors.iter().map(|color| color::vary_color(*color, *color_variance, rng)).collect(),
+                frequency.pick(rng)),
+            BehaviorDef::Flammable { ignition_temperature: Some(ignition_temperature), burns_into, .. } => Flammable::boxed_with_temperature(
+                *ignition_temperature,
+                materials.expect_id(burns_into)),
+            BehaviorDef::Flammable { ignition_rate, ignition_radius, burns_into, .. } => Flammable::boxed(
+                *ignition_rate,
+                *ignition_radius,
+                materials.expect_id(burns_into)),
+            BehaviorDef::DieWhenCrushed { crushing_probability } => DieWhenCrushed::boxed(*crushing_probability),
+            BehaviorDef::Igniter => Igniter::boxed(),
+            BehaviorDef::SidewaysMotionFallback => SidewaysMotionFallback::boxed(&position),
+            BehaviorDef::CurrentMotion { swap_probability_per_sec } => CurrentMotion::boxed(*swap_probability_per_sec),
+            BehaviorDef::HeatSource { temperature } => HeatSource::boxed(*temperature),
+            BehaviorDef::Liquid { dispersion_rate, viscosity } => Liquid::boxed(*dispersion_rate, *viscosity),
+            BehaviorDef::Gas { diffusion_rate } => Gas::boxed(*diffusion_rate),
+            BehaviorDef::Explosive { radius, strength, impulse, ignition_temperature, fire, smoke } => Explosive::boxed(
+                *radius,
+                *strength,
+                *impulse,
+                *ignition_temperature,
+                materials.expect_id(fire),
+                materials.expect_id(smoke)),
+            BehaviorDef::Corrosive { rate, strength, gas, gas_probability } => Corrosive::boxed(
+                *rate,
+                *strength,
+                gas.as_ref().map(|gas| materials.expect_id(gas)),
+                *gas_probability),
+            BehaviorDef::Emitter { material, rate } => Emitter::boxed(materials.expect_id(material), *rate),
+            BehaviorDef::Grows { rate, max_size, stem, sway_probability, branch_probability, water } => Grows::boxed(
+                *rate,
+                *max_size,
+                *sway_probability,
+                *branch_probability,
+                materials.expect_id(stem),
+                materials.expect_id(water)),
+        }
+    }
+
+    /// Whether the behavior moves the particle
+    pub fn moves(&self) -> bool {
+        matches!(self,
+            BehaviorDef::MoveDown { .. }
+            | BehaviorDef::Liquid { .. }
+            | BehaviorDef::Gas { .. }
+            | BehaviorDef::CurrentMotion { .. }
+            | BehaviorDef::SidewaysMotionFallback)
+    }
+
+    // Checks the parameters, and that the referenced materials exist
+    fn validate(&self, materials: &MaterialRegistry) -> Result<(), String> {
+        let check_range = |name: &str, range: &ValueRange| {
+            if range.min() > range.max() {
+                Err(format!("{} has its minimum greater than its maximum", name))
+            } else {
+                Ok(())
+            }
+        };
+        let check_material = |name: &str| {
+            if materials.id_of(name).is_none() {
+                Err(format!("unknown material \"{}\"", name))
+            } else {
+                Ok(())
+            }
+        };
+
+        match self {
+            BehaviorDef::LimitedLife { lifetime, spawn } => {
+                check_range("lifetime", lifetime)?;
+                if lifetime.min() <= 0. {
+                    return Err("lifetime must be strictly positive".to_string());
+                }
+                if let Some(spawn) = spawn {
+                    if spawn.distance.0 < 0 || spawn.distance.1 < 0 {
+                        return Err("the spawn distance cannot be negative".to_string());
+                    }
+                    check_material(&spawn.material)?;
+                }
+                Ok(())
+            },
+            BehaviorDef::AnimatedColor { colors, frequency, color_variance } => {
+                check_range("frequency", frequency)?;
+                if colors.is_empty() {
+                    return Err("AnimatedColor needs at least one color".to_string());
+                }
+                check_color_variance(*color_variance)
+            },
+            BehaviorDef::Flammable { ignition_radius, burns_into, .. } => {
+                // Igniters further away would not wake the particle
+                if *ignition_radius < 0 || *ignition_radius > WAKE_RADIUS {
+                    return Err(format!("ignition_radius must be between 0 and {}", WAKE_RADIUS));
+                }
+                check_material(burns_into)
+            },
+            BehaviorDef::Explosive { radius, fire, smoke, .. } => {
+                if *radius < 1 || *radius > MAX_BLAST_RADIUS {
+                    return Err(format!("radius must be between 1 and {}", MAX_BLAST_RADIUS));
+                }
+                check_material(fire)?;
+                check_material(smoke)
+            },
+            BehaviorDef::Liquid { dispersion_rate, viscosity } => {
+                if *dispersion_rate < 1 {
+                    return Err("dispersion_rate must be at least 1".to_string());
+                }
+                if !(0. ..1.).contains(viscosity) {
+                    return Err("viscosity must be between 0 (included) and 1 (excluded)".to_string());
+                }
+                Ok(())
+            },
+            BehaviorDef::Corrosive { rate, strength, gas, gas_probability } => {
+                if *rate < 0. || *strength <= 0. {
+                    return Err("the rate of Corrosive must be positive, and its strength strictly positive".to_string());
+                }
+                if !(0. ..=1.).contains(gas_probability) {
+                    return Err("gas_probability must be between 0 and 1".to_string());
+                }
+                match gas {
+                    Some(gas) => check_material(gas),
+                    None => Ok(()),
+                }
+            },
+            BehaviorDef::Emitter { material, rate } => {
+                if *rate < 0. {
+                    return Err("the rate of Emitter cannot be negative".to_string());
+                }
+                check_material(material)
+            },
+            BehaviorDef::Grows { rate, sway_probability, branch_probability, stem, water, .. } => {
+                if *rate < 0. {
+                    return Err("the rate of Grows cannot be negative".to_string());
+                }
+                if !(0. ..=1.).contains(sway_probability) || !(0. ..=1.).contains(branch_probability) {
+                    return Err("sway_probability and branch_probability must be between 0 and 1".to_string());
+                }
+                check_material(stem)?;
+                check_material(water)
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_color_variance(variance: i8) -> Result<(), String> {
+    if !(0..=color::MAX_COLOR_VARIANCE).contains(&variance) {
+        return Err(format!("color_variance must be between 0 and {}", color::MAX_COLOR_VARIANCE));
+    }
+    Ok(())
+}
+
+/// A change of material when the temperature crosses a threshold, see `PhaseTransition`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionDef {
+    #[serde(default)]
+    pub above: Option<f64>, // Exactly one of `above` and `below` must be set (°C)
+    #[serde(default)]
+    pub below: Option<f64>,
+    pub into: String,
+    #[serde(default)]
+    pub latent_heat: f64, // How far past the threshold the temperature must go, the new particle starting at the threshold (°C)
+}
+
+impl TransitionDef {
+    pub fn build(&self, materials: &MaterialRegistry) -> Box<dyn Behavior> {
+        let into = materials.expect_id(&self.into);
+        match (self.above, self.below) {
+            (Some(threshold), _) => PhaseTransition::boxed_above(threshold, into, self.latent_heat),
+            (None, Some(threshold)) => PhaseTransition::boxed_below(threshold, into, self.latent_heat),
+            (None, None) => unreachable!("Transitions are checked when the registry is built"),
+        }
+    }
+
+    fn validate(&self, materials: &MaterialRegistry) -> Result<(), String> {
+        if self.above.is_some() == self.below.is_some() {
+            return Err(format!("the transition into \"{}\" needs exactly one of `above` and `below`", self.into));
+        }
+        if self.latent_heat < 0. {
+            return Err("the latent heat of a transition cannot be negative".to_string());
+        }
+        if materials.id_of(&self.into).is_none() {
+            return Err(format!("unknown material \"{}\"", self.into));
+        }
+        Ok(())
+    }
+}
+
+/// How a static material breaks when a falling structure lands, see `Grid::collapse_structures`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShatterDef {
+    pub speed: f64, // Impact speed from which the particle shatters (cells/s)
+    pub into: String,
+}
+
+impl ShatterDef {
+    fn validate(&self, materials: &MaterialRegistry) -> Result<(), String> {
+        if self.speed < 0. {
+            return Err("the shatter speed cannot be negative".to_string());
+        }
+        if materials.id_of(&self.into).is_none() {
+            return Err(format!("unknown material \"{}\"", self.into));
+        }
+        Ok(())
+    }
+}
+
+/// Default brush used to paint a material
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrushDef {
+    #[serde(default)]
+    pub key: Option<String>, // Name of the key selecting the brush in the app
+    pub size: i32,
+    pub probability: f32,
+}
+
+/// State of matter of a material. Denser particles sink through liquids and gases, but not through solids and powders.
+/// `Static` solids never move: they cannot have moving behaviors, and the grid skips them when they have no behaviors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    #[default]
+    Solid,
+    Static,
+    Powder,
+    Liquid,
+    Gas,
+}
+
+impl Phase {
+    pub fn is_fluid(self) -> bool {
+        matches!(self, Phase::Liquid | Phase::Gas)
+    }
+
+    pub fn is_solid(self) -> bool {
+        matches!(self, Phase::Solid | Phase::Static)
+    }
+}
+
+/// `Material` describes a kind of particle: how it looks and which behaviors it has
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Material {
+    pub id: ParticleId,
+    pub name: String,
+    pub color: Color,
+    #[serde(default)]
+    pub color_variance: i8,
+    #[serde(default)]
+    pub behaviors: Vec<BehaviorDef>,
+    #[serde(default)]
+    pub brush: Option<BrushDef>,
+
+    #[serde(default)]
+    pub phase: Phase,
+    #[serde(default = "default_density")]
+    pub density: f64, // Used to decide which particle sinks when two fluids meet (kg/m³)
+    #[serde(default = "default_pressure")]
+    pub pressure: f64, // Pressure of new particles, for gases (see `Grid::spread_pressure`)
+    #[serde(default)]
+    pub strength: Option<f64>, // Pressure a gas needs to burst through the particle, never if not set
+    #[serde(default)]
+    pub corrosion_resistance: f64, // Probability of withstanding each attack of a `Corrosive` particle, immune at 1
+    #[serde(default)]
+    pub anchored: bool, // Holds up the structure it is part of, for static materials
+    #[serde(default)]
+    pub shatter: Option<ShatterDef>, // Breaking of static particles landing too fast, they land intact if not set
+
+    #[serde(default = "default_temperature")]
+    pub temperature: f64, // Temperature of new particles (°C)
+    #[serde(default = "default_thermal_conductivity")]
+    pub thermal_conductivity: f64, // How fast heat goes through the particle (1/s)
+    #[serde(default = "default_heat_capacity")]
+    pub heat_capacity: f64, // How much heat it takes to change the temperature of the particle
+    #[serde(default)]
+    pub cooling_rate: f64, // How fast the particle goes back to `AMBIENT_TEMPERATURE` by itself (1/s)
+    #[serde(default)]
+    pub transitions: Vec<TransitionDef>,
+}
+
+fn default_density() -> f64 {
+    1000.
+}
+
+fn default_pressure() -> f64 {
+    AMBIENT_PRESSURE
+}
+
+fn default_temperature() -> f64 {
+    AMBIENT_TEMPERATURE
+}
+
+fn default_thermal_conductivity() -> f64 {
+    1.
+}
+
+fn default_heat_capacity() -> f64 {
+    1.
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialFile {
+    material: Vec<Material>,
+    #[serde(default)]
+    reaction: Vec<ReactionDef>,
+}
+
+/// `MaterialRegistry` holds every material the simulation knows about, and creates their particles
+pub struct MaterialRegistry {
+    materials: Vec<Option<Material>>, // Indexed by id
+    ids: HashMap<String, ParticleId>,
+    reactions: ReactionTable,
+}
+
+impl Default for MaterialRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl MaterialRegistry {
+    /// The materials defined in `materials.toml`
+    pub fn builtin() -> Self {
+        Self::from_toml_str(BUILTIN_MATERIALS).expect("The built-in materials should be valid")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MaterialError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self, MaterialError> {
+        let file: MaterialFile = toml::from_str(content)?;
+        Self::from_materials(file.material)?.with_reactions(&file.reaction)
+    }
+
+    pub fn from_materials(materials: Vec<Material>) -> Result<Self, MaterialError> {
+        let mut res = Self {
+            materials: vec![None; ParticleId::MAX as usize + 1],
+            ids: HashMap::new(),
+            reactions: ReactionTable::empty(),
+        };
+
+        for material in materials {
+            if res.materials[material.id as usize].is_some() {
+                return Err(MaterialError::Invalid(format!("the id {} is used by several materials", material.id)));
+            }
+            if res.ids.insert(material.name.clone(), material.id).is_some() {
+                return Err(MaterialError::Invalid(format!("the name \"{}\" is used by several materials", material.name)));
+            }
+            let id = material.id as usize;
+            res.materials[id] = Some(material);
+        }
+
+        if res.get(EMPTY_ID).is_none() {
+            return Err(MaterialError::Invalid(format!("the empty material (id {}) is not defined", EMPTY_ID)));
+        }
+        for material in res.iter() {
+            if material.heat_capacity <= 0. || material.thermal_conductivity < 0. || material.cooling_rate < 0. {
+                return Err(MaterialError::Invalid(format!("{}: the heat capacity must be strictly positive, the conductivity and cooling rate positive", material.name)));
+            }
+            if material.density <= 0. || material.pressure <= 0. {
+                return Err(MaterialError::Invalid(format!("{}: the density and pressure must be strictly positive", material.name)));
+            }
+            if material.phase == Phase::Static && material.behaviors.iter().any(BehaviorDef::moves) {
+                return Err(MaterialError::Invalid(format!("{}: static materials cannot have behaviors that move them", material.name)));
+            }
+            check_color_variance(material.color_variance).map_err(|message| MaterialError::Invalid(format!("{}: {}", material.name, message)))?;
+            if !(0. ..=1.).contains(&material.corrosion_resistance) {
+                return Err(MaterialError::Invalid(format!("{}: the corrosion resistance must be between 0 and 1", material.name)));
+            }
+            for behavior in &material.behaviors {
+                behavior.validate(&res).map_err(|message| MaterialError::Invalid(format!("{}: {}", material.name, message)))?;
+            }
+            if let Some(shatter) = &material.shatter {
+                if material.phase != Phase::Static {
+                    return Err(MaterialError::Invalid(format!("{}: only static materials can shatter", material.name)));
+                }
+                shatter.validate(&res).map_err(|message| MaterialError::Invalid(format!("{}: {}", material.name, message)))?;
+            }
+            for transition in &material.transitions {
+                transition.validate(&res).map_err(|message| MaterialError::Invalid(format!("{}: {}", material.name, message)))?;
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Replaces the reactions between the materials (see `ReactionTable`)
+    pub fn with_reactions(mut self, reactions: &[ReactionDef]) -> Result<Self, MaterialError> {
+        self.reactions = ReactionTable::new(reactions, &self).map_err(MaterialError::Invalid)?;
+        Ok(self)
+    }
+
+    pub fn reactions(&self) -> &ReactionTable {
+        &self.reactions
+    }
+
+    pub fn get(&self, id: ParticleId) -> Option<&Material> {
+        self.materials[id as usize].as_ref()
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<ParticleId> {
+        self.ids.get(name).copied()
+    }
+
+    /// Every material, by increasing id
+    pub fn iter(&self) -> impl Iterator<Item = &Material> {
+        self.materials.iter().flatten()
+    }
+
+    /// Creates a new particle of the given material. Panics if the material does not exist.
+    pub fn create(&self, id: ParticleId, position: Position, rng: &mut SimRng) -> Particle {
+        let material = self.get(id).unwrap_or_else(|| panic!("Unknown material id {}", id));
+        let color = if material.color_variance != 0 {
+            color::vary_color(material.color, material.color_variance, rng)
+        } else {
+            material.color
+        };
+        let behaviors = material.behaviors.iter()
+            .map(|behavior| behavior.build(position, self, rng))
+            .chain(material.transitions.iter().map(|transition| transition.build(self)))
+            .collect();
+
+        let mut particle = Particle::new(position, color, id, behaviors);
+        particle.set_temperature(material.temperature);
+        particle.set_pressure(material.pressure);
+        particle
+    }
+
+    // Names are checked when the registry is built
+    fn expect_id(&self, name: &str) -> ParticleId {
+        self.id_of(name).unwrap_or_else(|| panic!("Unknown material \"{}\"", name))
+    }
+}
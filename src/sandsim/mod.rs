@@ -2,4 +2,15 @@ pub mod grid;
 pub mod particle;
 pub mod brush_settings;
 pub mod behaviors;
-pub mod particle_action;
\ No newline at end of file
+pub mod particle_action;
+pub mod clock;
+pub mod cell_buffer;
+pub mod neighbourhood;
+pub mod chunks;
+pub mod material;
+pub mod reaction;
+pub mod snapshot;
+pub mod level;
+pub mod render;
+pub mod recorder;
+mod region;
\ No newline at end of file
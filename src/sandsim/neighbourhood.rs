@@ -0,0 +1,107 @@
+use crate::sandsim::behaviors::{BehaviorId, BehaviorSet};
+use crate::sandsim::cell_buffer::CellWindow;
+use crate::sandsim::grid::Position;
+use crate::sandsim::material::{Material, MaterialRegistry};
+use crate::sandsim::particle::ParticleId;
+
+/// `Neighbourhood` is the view of the grid given to behaviors during an update.
+/// It exposes the particle and behavior ids of the cells around the particle, as seen at this point of the tick,
+/// and lets behaviors move these ids around when their particle moves.
+/// Only a window of the grid may be reachable (see `in_bounds`), e.g. during a parallel update.
+pub struct Neighbourhood<'a, 'b> {
+    particle_ids: &'a mut CellWindow<'b, ParticleId>,
+    behaviors_ids: &'a mut CellWindow<'b, BehaviorSet>,
+    materials: &'b MaterialRegistry,
+
+    touched: Vec<Position>, // Cells whose ids were modified, to be synchronized back by the grid
+}
+
+impl<'a, 'b> Neighbourhood<'a, 'b> {
+    pub fn new(particle_ids: &'a mut CellWindow<'b, ParticleId>, behaviors_ids: &'a mut CellWindow<'b, BehaviorSet>, materials: &'b MaterialRegistry) -> Self {
+        debug_assert!(particle_ids.bounds() == behaviors_ids.bounds());
+        Self { particle_ids, behaviors_ids, materials, touched: vec![] }
+    }
+
+    /// Width of the whole grid
+    pub fn width(&self) -> i32 {
+        self.particle_ids.width()
+    }
+
+    /// Height of the whole grid
+    pub fn height(&self) -> i32 {
+        self.particle_ids.height()
+    }
+
+    /// Whether the cell can be reached. Cells outside of the grid, or too far away from the particle, cannot.
+    pub fn in_bounds(&self, position: Position) -> bool {
+        self.particle_ids.in_bounds(position)
+    }
+
+    /// Whether the cell is in the grid but too far away from the particle, which happens during a parallel update.
+    /// Such a cell is not an obstacle: the particle should wait for a later update to go there.
+    pub fn out_of_reach(&self, (x, y): Position) -> bool {
+        x >= 0 && x < self.width() && y >= 0 && y < self.height() && !self.in_bounds((x, y))
+    }
+
+    /// Id of the particle at the given position. The position must be in bounds.
+    pub fn particle_id(&self, position: Position) -> ParticleId {
+        self.particle_ids[position]
+    }
+
+    /// Behaviors of the particle at the given position. The position must be in bounds.
+    pub fn behaviors_ids(&self, position: Position) -> BehaviorSet {
+        self.behaviors_ids[position]
+    }
+
+    /// Whether the particle at the given position has the behavior. Out of bounds positions have no behavior.
+    pub fn has_behavior(&self, position: Position, behavior_id: BehaviorId) -> bool {
+        self.behaviors_ids.get(position).is_some_and(|ids| ids.contains(behavior_id))
+    }
+
+    /// Material of the particle at the given position. The position must be in bounds.
+    pub fn material(&self, position: Position) -> &Material {
+        let id = self.particle_ids[position];
+        self.materials.get(id).unwrap_or_else(|| panic!("Unknown material id {}", id))
+    }
+
+    /// Density of the particle at the given position. The position must be in bounds.
+    pub fn density(&self, position: Position) -> f64 {
+        self.material(position).density
+    }
+
+    /// Whether the particle at the given position is a liquid or a gas. Out of bounds positions are not.
+    pub fn is_fluid(&self, position: Position) -> bool {
+        self.in_bounds(position) && self.material(position).phase.is_fluid()
+    }
+
+    /// Whether the particle at `from` can swap places with the one at `to`, which has to be a liquid or a gas:
+    /// a lighter one to go down or sideways, a heavier one to go up
+    pub fn can_displace(&self, from: Position, to: Position) -> bool {
+        self.can_displace_with(self.density(from), to.1 - from.1, to)
+    }
+
+    /// Same as `can_displace`, for a particle of the given density going up (negative `dy`), down or sideways to `to`
+    pub fn can_displace_with(&self, density: f64, dy: i32, to: Position) -> bool {
+        if !self.is_fluid(to) {
+            return false;
+        }
+        if dy < 0 {
+            density < self.density(to)
+        } else {
+            density > self.density(to)
+        }
+    }
+
+    /// Swaps the ids of two cells, to reflect a particle moving from one to the other
+    pub fn swap(&mut self, a: Position, b: Position) {
+        self.particle_ids.swap(a, b);
+        self.behaviors_ids.swap(a, b);
+        self.touched.push(a);
+        self.touched.push(b);
+    }
+
+    /// Cells whose ids were modified through this view
+    pub fn take_touched(&mut self) -> Vec<Position> {
+        std::mem::take(&mut self.touched)
+    }
+}
@@ -1,24 +1,41 @@
-use rand::Rng;
-use sdl2::pixels::Color;
+use crate::color::Color;
 
-use crate::color;
 use crate::sandsim::behaviors::*;
 use crate::sandsim::particle_action::ParticleAction;
-use crate::sandsim::grid::Position;
-
-pub const SAND_CELL_COLOR: Color = Color { r: 246, g: 215, b: 176, a: 255 };
-pub const EMPTY_CELL_COLOR: Color = Color { r: 0, g: 0, b: 0, a: 255 };
-pub const WOOD_CELL_COLOR: Color = Color { r: 68, g: 48, b: 34, a: 255 };
-pub const SMOKE_CELL_COLOR: Color = Color { r: 76, g: 74, b: 77, a: 255 };
-pub const WATER_CELL_COLOR: Color = Color { r: 30, g: 120, b: 190, a: 255 };
+use crate::sandsim::grid::{Position, SimRng};
+use crate::sandsim::neighbourhood::Neighbourhood;
+use crate::sandsim::snapshot::{SnapshotError, StateReader, StateWriter};
 
 pub type ParticleId = u8;
+
+/// Temperature of new particles, unless their material says otherwise (°C)
+pub const AMBIENT_TEMPERATURE: f64 = 20.;
+/// Pressure of the surrounding air, and of new particles unless their material says otherwise
+pub const AMBIENT_PRESSURE: f64 = 1.;
+
+// Ids of the built-in materials, defined in `materials.toml`
 pub const EMPTY_ID: ParticleId = 0;
 pub const SAND_ID: ParticleId = 1;
 pub const WOOD_ID: ParticleId = 2;
 pub const SMOKE_ID: ParticleId = 3;
 pub const FIRE_ID: ParticleId = 4;
 pub const WATER_ID: ParticleId = 5;
+pub const STEAM_ID: ParticleId = 6;
+pub const ICE_ID: ParticleId = 7;
+pub const OIL_ID: ParticleId = 8;
+pub const HONEY_ID: ParticleId = 9;
+pub const GUNPOWDER_ID: ParticleId = 10;
+pub const TNT_ID: ParticleId = 11;
+pub const SALT_ID: ParticleId = 12;
+pub const SALT_WATER_ID: ParticleId = 13;
+pub const ACID_ID: ParticleId = 14;
+pub const FUMES_ID: ParticleId = 15;
+pub const STONE_ID: ParticleId = 16;
+pub const LAVA_ID: ParticleId = 17;
+pub const SEED_ID: ParticleId = 18;
+pub const PLANT_ID: ParticleId = 19;
+pub const METAL_ID: ParticleId = 20;
+pub const GLASS_ID: ParticleId = 21;
 
 pub struct Particle {
     state: ParticleState,
@@ -26,6 +43,7 @@ pub struct Particle {
 
     modified: bool,
     required_actions: Vec<ParticleAction>,
+    last_update_tick: u64, // So that a particle moving to a cell visited later during the same tick is not updated twice
 }
 
 #[derive(Clone)]
@@ -34,18 +52,21 @@ pub struct ParticleState {
     pub position: Position,
     
     pub particle_id: ParticleId,
-    pub behaviors_ids: BehaviorId,
+    pub behaviors_ids: BehaviorSet,
+    pub temperature: f64, // In °C, exchanged with the neighbours by `Grid::diffuse_heat`
+    pub pressure: f64, // Amount of gas in the cell, relative to the surrounding air. Spread by `Grid::spread_pressure`.
+    pub velocity: FloatPosition, // In cells per second, used by `MoveDown`
 }
 
 impl Particle {
-    pub fn update(&mut self, position: Position, dt: f64, grid: &mut Vec<Vec<ParticleId>>, behaviors_grid: &mut Vec<Vec<BehaviorId>>) -> bool { 
+    pub fn update(&mut self, position: Position, dt: f64, neighbourhood: &mut Neighbourhood, rng: &mut SimRng) -> bool { 
         let mut actions = vec![];
         self.modified = false;
         self.state.position = position; // Reset position to the new one, in case it was changed my another behavior
         self.required_actions = vec![];
 
         for behavior in self.behaviors.iter_mut() {
-            actions.extend(behavior.update(&mut self.state, dt, grid, behaviors_grid));
+            actions.extend(behavior.update(&mut self.state, dt, neighbourhood, rng));
         }
 
         for action in &actions {
@@ -60,14 +81,14 @@ impl Particle {
             ParticleAction::SetPosition { position } =>  {
                 self.state.position = *position;
             },
-            ParticleAction::KillParticle { .. } => {
+            ParticleAction::KillParticle { .. } | ParticleAction::BlastParticle { .. } => {
                 // Pass it to the grid
                 self.required_actions.push(action.clone());
             },
             ParticleAction::SetColor { color } => {
                 self.state.color = *color;
             },
-            ParticleAction::SpawnParticle { .. } => {
+            ParticleAction::SpawnParticle { .. } | ParticleAction::Transform { .. } | ParticleAction::ApplyImpulse { .. } => {
                 // Pass it to the grid
                 self.required_actions.push(action.clone());
             }
@@ -75,6 +96,33 @@ impl Particle {
         self.modified = true;
     }
 
+    /// The particle is destroyed by a blast: returns what its behaviors do before it disappears (see `Behavior::detonate`)
+    pub fn detonate(&mut self, position: Position, neighbourhood: &Neighbourhood, rng: &mut SimRng) -> Vec<ParticleAction> {
+        self.state.position = position;
+        let mut actions = vec![];
+        for behavior in self.behaviors.iter_mut() {
+            actions.extend(behavior.detonate(&self.state, neighbourhood, rng));
+        }
+        actions
+    }
+
+    pub fn apply_impulse(&mut self, velocity: FloatPosition) {
+        self.state.velocity = (self.state.velocity.0 + velocity.0, self.state.velocity.1 + velocity.1);
+    }
+
+    /// Whether the particle still has something to do even if its surroundings do not change
+    pub fn is_awake(&self) -> bool {
+        self.behaviors.iter().any(|behavior| !behavior.is_idle())
+    }
+
+    pub fn get_last_update_tick(&self) -> u64 {
+        self.last_update_tick
+    }
+
+    pub fn set_last_update_tick(&mut self, tick: u64) {
+        self.last_update_tick = tick;
+    }
+
     pub fn get_color(&self) -> Color {
         self.state.color
     }
@@ -87,7 +135,7 @@ impl Particle {
         self.required_actions.clone()
     }
 
-    pub fn get_behaviors_ids(&self) -> BehaviorId {
+    pub fn get_behaviors_ids(&self) -> BehaviorSet {
         self.state.behaviors_ids
     }
 
@@ -95,12 +143,70 @@ impl Particle {
         self.state.position
     }
 
+    /// In cells per second, see `ParticleState::velocity`
+    pub fn get_velocity(&self) -> FloatPosition {
+        self.state.velocity
+    }
+
+    pub fn get_temperature(&self) -> f64 {
+        self.state.temperature
+    }
+
+    pub fn set_temperature(&mut self, temperature: f64) {
+        self.state.temperature = temperature;
+    }
+
+    pub fn get_pressure(&self) -> f64 {
+        self.state.pressure
+    }
+
+    pub fn set_pressure(&mut self, pressure: f64) {
+        self.state.pressure = pressure;
+    }
+
+    /// Writes the color, temperature, pressure and velocity of the particle and the state of its behaviors
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_color(self.state.color);
+        writer.write_f64(self.state.temperature);
+        writer.write_f64(self.state.pressure);
+        writer.write_f64(self.state.velocity.0);
+        writer.write_f64(self.state.velocity.1);
+        writer.write_u8(self.behaviors.len() as u8);
+        for behavior in &self.behaviors {
+            writer.write_u8(behavior.get_id().index() as u8);
+            writer.write_block(|block| behavior.save_state(block));
+        }
+    }
+
+    /// Restores the state written by `save_state` on a particle of the same material.
+    /// `behavior_ids` maps the behavior ids found in the snapshot to the current ones.
+    pub fn load_state(&mut self, reader: &mut StateReader, behavior_ids: &[Option<BehaviorId>]) -> Result<(), SnapshotError> {
+        self.state.color = reader.read_color()?;
+        self.state.temperature = reader.read_f64()?;
+        self.state.pressure = reader.read_f64()?;
+        self.state.velocity = (reader.read_f64()?, reader.read_f64()?);
+        let count = reader.read_u8()? as usize;
+        if count != self.behaviors.len() {
+            return Err(SnapshotError::Invalid(format!("a particle has {} behaviors, its material has {}", count, self.behaviors.len())));
+        }
+
+        for behavior in self.behaviors.iter_mut() {
+            let saved_id = reader.read_u8()? as usize;
+            if behavior_ids.get(saved_id).copied().flatten() != Some(behavior.get_id()) {
+                return Err(SnapshotError::Invalid("the behaviors of a material changed since the snapshot was saved".to_string()));
+            }
+            let mut block = reader.read_block()?;
+            behavior.load_state(&mut block)?;
+            if !block.is_empty() {
+                return Err(SnapshotError::Invalid("a behavior state is longer than expected".to_string()));
+            }
+        }
+        Ok(())
+    }
+
     pub fn new(position: Position, color: Color, particle_id: ParticleId, behaviors: Vec<Box<dyn Behavior>>) -> Self {
         // Construct behaviors id
-        let mut behaviors_ids = 0;
-        for behavior in &behaviors {
-            behaviors_ids |= behavior.get_id();
-        }
+        let behaviors_ids = behaviors.iter().map(|behavior| behavior.get_id()).collect();
         
         Self {
             state: ParticleState {
@@ -108,77 +214,14 @@ impl Particle {
                 position,
                 particle_id,
                 behaviors_ids,
+                temperature: AMBIENT_TEMPERATURE,
+                pressure: AMBIENT_PRESSURE,
+                velocity: (0., 0.),
             },
             modified: false,
             behaviors,
             required_actions: vec![],
+            last_update_tick: 0,
         }
     }
-
-    pub fn new_sand(position: Position) -> Self {
-        let behaviors = vec![
-            MoveDown::boxed(position, 8.0 * 60., 0.1 * 60. * 60.),
-        ];
-        Self::new(position, color::vary_color(SAND_CELL_COLOR, 10), SAND_ID, behaviors)
-    }
-
-    pub fn new_empty(position: Position) -> Self {
-        let behaviors = vec![
-            AirLike::boxed(),
-        ];
-        Self::new(position, EMPTY_CELL_COLOR, EMPTY_ID, behaviors)
-    }
-
-    pub fn new_wood(position: Position) -> Self {
-        Self::new(
-            position,
-            color::vary_color(WOOD_CELL_COLOR, 10),
-            WOOD_ID,
-            vec![
-                Flammable::boxed(0.1, 3),
-            ])
-    }
-
-    pub fn new_smoke(position: Position) -> Self {
-        let lifetime = rand::thread_rng().gen_range(4.0..=7.5);
-
-        let behaviors = vec![
-            MoveDown::boxed(position, 0.5 * 60., -0.003 * 60. * 60.),
-            AirLike::boxed(),
-            LimitedLife::boxed(lifetime),
-        ];
-        Self::new(position, color::vary_color(SMOKE_CELL_COLOR, 3), SMOKE_ID, behaviors)
-    }
-
-    pub fn new_fire(position: Position) -> Self {
-        let mut rng = rand::thread_rng();
-        let lifetime = rng.gen_range(1.0..=3.0);
-        let frequency = rng.gen_range(5.0..=10.);
-        let behaviors = vec![
-            LimitedLife::boxed_with_spawn(
-                lifetime,
-                0.85,
-                |pos| Self::new_smoke(pos),
-                (1, 1)),
-            AnimatedColor::boxed(vec![
-                color::vary_color(Color::RGBA(84, 30, 30, 255), 10),
-                color::vary_color(Color::RGBA(255, 31, 31, 255), 10),
-                color::vary_color(Color::RGBA(234, 90, 0, 255), 10),
-                color::vary_color(Color::RGBA(255, 105, 0, 255), 10),
-                color::vary_color(Color::RGBA(238, 204, 9, 255), 10),
-            ], frequency),
-            DieWhenCrushed::boxed(0.5),
-            Igniter::boxed(),
-        ];
-        Self::new(position, Color::YELLOW, FIRE_ID, behaviors)
-    }
-
-    pub fn new_water(position: Position) -> Self {
-        let behaviors = vec![
-            MoveDown::boxed(position, 8.0 * 60., 0.1 * 60. * 60.),
-            SidewaysMotionFallback::boxed(&position),
-            CurrentMotion::boxed(0.25),
-        ];
-        Self::new(position, color::vary_color(WATER_CELL_COLOR, 3), WATER_ID, behaviors)
-    }
 }
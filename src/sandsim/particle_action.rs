@@ -1,12 +1,19 @@
-use sdl2::pixels::Color;
+use crate::color::Color;
 
+use crate::sandsim::behaviors::FloatPosition;
 use crate::sandsim::grid::Position;
-use crate::sandsim::particle::Particle;
+use crate::sandsim::particle::ParticleId;
 
 #[derive(Clone)]
 pub enum ParticleAction {
     SetPosition{position: Position},
     KillParticle{position: Position},
-    SpawnParticle{callback: fn(Position) -> Particle, position: Position},
+    /// Destroys the particle at the given position in a blast, which sets it off if it is explosive (see `Behavior::detonate`)
+    BlastParticle{position: Position},
+    SpawnParticle{material: ParticleId, position: Position},
     SetColor{color: Color},
+    /// Replaces the particle, wherever it ends up after this update, by a particle of another material at the given temperature
+    Transform{material: ParticleId, temperature: f64},
+    /// Adds to the velocity of the particle at the given position (cells per second)
+    ApplyImpulse{position: Position, velocity: FloatPosition},
 }
\ No newline at end of file
@@ -0,0 +1,228 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::sandsim::grid::SimRng;
+use crate::sandsim::material::MaterialRegistry;
+use crate::sandsim::particle::*;
+
+/// A reaction between two neighbouring materials, as declared in a material file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReactionDef {
+    pub reactants: [String; 2],
+    pub probability: f64, // Chances per second for a pair of neighbours to react
+    pub products: Vec<String>, // Replace the reactants in the same order, the second reactant disappears if there is a single product
+}
+
+impl ReactionDef {
+    fn validate(&self, materials: &MaterialRegistry) -> Result<(), String> {
+        if self.products.is_empty() || self.products.len() > 2 {
+            return Err("a reaction needs one or two products".to_string());
+        }
+        if self.probability < 0. {
+            return Err("the probability of a reaction cannot be negative".to_string());
+        }
+        for name in self.reactants.iter().chain(&self.products) {
+            if materials.id_of(name).is_none() {
+                return Err(format!("unknown material \"{}\"", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Two materials reacting, once resolved by the `ReactionTable`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reaction {
+    pub reactants: (ParticleId, ParticleId),
+    pub probability: f64,
+    pub products: (ParticleId, ParticleId),
+}
+
+impl Reaction {
+    /// Whether `a` and `b` are the reactants, in this order
+    pub fn matches(&self, a: ParticleId, b: ParticleId) -> bool {
+        self.reactants == (a, b)
+    }
+}
+
+/// `ReactionTable` holds the reactions between materials, applied to neighbouring cells by `Grid::react`.
+/// The first reaction declared for a pair of materials wins, whichever side each of them is on:
+///
+/// ```
+/// use sandgamebase::sandsim::material::MaterialRegistry;
+/// use sandgamebase::sandsim::particle::*;
+///
+/// let materials = MaterialRegistry::builtin();
+/// let reactions = materials.reactions();
+/// assert_eq!(reactions.products(WATER_ID, FIRE_ID), Some((STEAM_ID, EMPTY_ID)));
+/// assert_eq!(reactions.products(FIRE_ID, WATER_ID), Some((EMPTY_ID, STEAM_ID)));
+/// assert_eq!(reactions.products(SAND_ID, WATER_ID), None);
+/// ```
+pub struct ReactionTable {
+    reactions: Vec<Reaction>,
+    by_material: Vec<Vec<usize>>, // Indices of the reactions naming each material, indexed by id
+}
+
+impl ReactionTable {
+    pub fn empty() -> Self {
+        Self {
+            reactions: vec![],
+            by_material: vec![vec![]; ParticleId::MAX as usize + 1],
+        }
+    }
+
+    /// Resolves the names of the materials. Fails on the first invalid definition.
+    pub fn new(definitions: &[ReactionDef], materials: &MaterialRegistry) -> Result<Self, String> {
+        let mut table = Self::empty();
+        for definition in definitions {
+            definition.validate(materials)
+                .map_err(|message| format!("{} + {}: {}", definition.reactants[0], definition.reactants[1], message))?;
+
+            let id = |name: &String| materials.id_of(name).unwrap();
+            let first = id(&definition.reactants[0]);
+            let second = id(&definition.reactants[1]);
+            let products = (id(&definition.products[0]), definition.products.get(1).map_or(EMPTY_ID, id));
+
+            let index = table.reactions.len();
+            table.reactions.push(Reaction { reactants: (first, second), probability: definition.probability, products });
+            table.by_material[first as usize].push(index);
+            if second != first {
+                table.by_material[second as usize].push(index);
+            }
+        }
+        Ok(table)
+    }
+
+    /// Whether the material is named in a reaction
+    pub fn involves(&self, id: ParticleId) -> bool {
+        !self.by_material[id as usize].is_empty()
+    }
+
+    /// The reaction between neighbours of materials `a` and `b`, if any, and whether they are the other way around in it
+    pub fn find(&self, a: ParticleId, b: ParticleId) -> Option<(&Reaction, bool)> {
+        self.by_material[a as usize].iter().chain(&self.by_material[b as usize])
+            .filter_map(|&index| {
+                let reaction = &self.reactions[index];
+                if reaction.matches(a, b) {
+                    Some((index, reaction, false))
+                } else if reaction.matches(b, a) {
+                    Some((index, reaction, true))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|(index, _, _)| *index)
+            .map(|(_, reaction, swapped)| (reaction, swapped))
+    }
+
+    /// What `a` and `b` turn into when they react, in the same order
+    pub fn products(&self, a: ParticleId, b: ParticleId) -> Option<(ParticleId, ParticleId)> {
+        self.find(a, b).map(|(reaction, swapped)| Self::ordered_products(reaction, swapped))
+    }
+
+    /// Rolls whether neighbours of materials `a` and `b` react during `dt` seconds, and returns what they turn into if they do
+    pub fn react(&self, a: ParticleId, b: ParticleId, dt: f64, rng: &mut SimRng) -> Option<(ParticleId, ParticleId)> {
+        let (reaction, swapped) = self.find(a, b)?;
+        if rng.gen::<f64>() >= reaction.probability * dt {
+            return None;
+        }
+        Some(Self::ordered_products(reaction, swapped))
+    }
+
+    fn ordered_products(reaction: &Reaction, swapped: bool) -> (ParticleId, ParticleId) {
+        let (first, second) = reaction.products;
+        if swapped { (second, first) } else { (first, second) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const A: ParticleId = 1;
+    const B: ParticleId = 2;
+    const C: ParticleId = 3;
+
+    fn materials() -> MaterialRegistry {
+        MaterialRegistry::from_toml_str(r#"
+            [[material]]
+            id = 0
+            name = "empty"
+            color = [0, 0, 0]
+            phase = "gas"
+
+            [[material]]
+            id = 1
+            name = "a"
+            color = [255, 0, 0]
+
+            [[material]]
+            id = 2
+            name = "b"
+            color = [0, 255, 0]
+
+            [[material]]
+            id = 3
+            name = "c"
+            color = [0, 0, 255]
+        "#).unwrap()
+    }
+
+    fn reaction(reactants: [&str; 2], probability: f64, products: &[&str]) -> ReactionDef {
+        ReactionDef {
+            reactants: reactants.map(String::from),
+            probability,
+            products: products.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    fn table(definitions: &[ReactionDef]) -> Result<ReactionTable, String> {
+        ReactionTable::new(definitions, &materials())
+    }
+
+    #[test]
+    fn products_follow_the_order_of_the_neighbours() {
+        let reactions = table(&[reaction(["a", "b"], 1., &["c", "a"])]).unwrap();
+        assert_eq!(reactions.products(A, B), Some((C, A)));
+        assert_eq!(reactions.products(B, A), Some((A, C)));
+        assert_eq!(reactions.products(A, C), None);
+        assert!(reactions.involves(A) && reactions.involves(B) && !reactions.involves(C));
+    }
+
+    #[test]
+    fn single_product_removes_the_second_reactant() {
+        let reactions = table(&[reaction(["a", "b"], 1., &["c"])]).unwrap();
+        assert_eq!(reactions.products(A, B), Some((C, EMPTY_ID)));
+        assert_eq!(reactions.products(B, A), Some((EMPTY_ID, C)));
+    }
+
+    #[test]
+    fn first_declared_reaction_wins() {
+        let reactions = table(&[
+            reaction(["b", "a"], 1., &["c", "c"]),
+            reaction(["a", "b"], 1., &["b", "a"]),
+        ]).unwrap();
+        assert_eq!(reactions.products(A, B), Some((C, C)));
+    }
+
+    #[test]
+    fn probability_is_per_second() {
+        let mut rng = SimRng::seed_from_u64(0);
+        let never = table(&[reaction(["a", "b"], 0., &["c"])]).unwrap();
+        assert!((0..100).all(|_| never.react(A, B, 1., &mut rng).is_none()));
+        let always = table(&[reaction(["a", "b"], 10., &["c"])]).unwrap();
+        assert!((0..100).all(|_| always.react(A, B, 0.1, &mut rng) == Some((C, EMPTY_ID))));
+    }
+
+    #[test]
+    fn invalid_reactions_are_rejected() {
+        assert!(table(&[reaction(["a", "d"], 1., &["c"])]).is_err());
+        assert!(table(&[reaction(["a", "b"], 1., &["d"])]).is_err());
+        assert!(table(&[reaction(["a", "b"], 1., &[])]).is_err());
+        assert!(table(&[reaction(["a", "b"], 1., &["a", "b", "c"])]).is_err());
+        assert!(table(&[reaction(["a", "b"], -1., &["c"])]).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::sandsim::grid::Grid;
+use crate::sandsim::render::ImageError;
+
+// Trade-off between the quality of the GIF palettes and the encoding time, from 1 (best) to 30
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// A single animated GIF
+    Gif,
+    /// Numbered PNG files (`frame-00000.png`, ...) in a directory
+    PngSequence,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecorderSettings {
+    pub format: RecordingFormat,
+    pub every_n_ticks: u32, // Only one tick out of `every_n_ticks` is captured
+    pub scale: u32, // Size of a cell in pixels
+    pub frame_delay: Duration, // Time between two frames of the animation
+    pub max_duration: Option<Duration>, // The recording stops once the animation is this long
+}
+
+impl Default for RecorderSettings {
+    fn default() -> Self {
+        Self {
+            format: RecordingFormat::Gif,
+            every_n_ticks: 2,
+            scale: 2,
+            frame_delay: Duration::from_millis(30),
+            max_duration: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+enum Output {
+    Gif(gif::Encoder<BufWriter<File>>),
+    PngSequence(PathBuf),
+}
+
+/// `Recorder` turns the successive states of a grid into an animation.
+/// Call `capture` after every update of the grid, then `finish`.
+pub struct Recorder {
+    settings: RecorderSettings,
+    output: Output,
+
+    ticks: u64, // Number of calls to `capture`
+    frames: u32,
+}
+
+impl Recorder {
+    /// Starts a recording to `path`: the GIF file, or the directory of the PNG sequence.
+    /// `width` and `height` are the size of the grid that will be recorded.
+    pub fn start(path: impl AsRef<Path>, width: i32, height: i32, settings: RecorderSettings) -> Result<Self, ImageError> {
+        assert!(settings.every_n_ticks > 0 && settings.scale > 0, "The recording period and scale must be at least 1");
+        let (image_width, image_height) = (width as u32 * settings.scale, height as u32 * settings.scale);
+
+        let output = match settings.format {
+            RecordingFormat::Gif => {
+                let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(image_width), u16::try_from(image_height)) else {
+                    return Err(ImageError::TooLarge { width: image_width, height: image_height });
+                };
+                let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), gif_width, gif_height, &[])?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Output::Gif(encoder)
+            },
+            RecordingFormat::PngSequence => {
+                std::fs::create_dir_all(&path)?;
+                Output::PngSequence(path.as_ref().to_path_buf())
+            },
+        };
+
+        Ok(Self { settings, output, ticks: 0, frames: 0 })
+    }
+
+    /// Number of frames recorded so far
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Whether the recording reached its maximum duration
+    pub fn is_finished(&self) -> bool {
+        self.settings.max_duration.is_some_and(|max_duration| self.settings.frame_delay * (self.frames + 1) > max_duration)
+    }
+
+    /// Records the grid if this tick is one of the captured ones. Does nothing once the recording is finished.
+    pub fn capture(&mut self, grid: &Grid) -> Result<(), ImageError> {
+        let captured = self.ticks.is_multiple_of(self.settings.every_n_ticks as u64);
+        self.ticks += 1;
+        if !captured || self.is_finished() {
+            return Ok(());
+        }
+
+        let mut image = grid.render(self.settings.scale);
+        match &mut self.output {
+            Output::Gif(encoder) => {
+                let mut frame = gif::Frame::from_rgba_speed(image.width as u16, image.height as u16, &mut image.data, GIF_QUANTIZATION_SPEED);
+                frame.delay = (self.settings.frame_delay.as_millis() / 10).clamp(1, u16::MAX as u128) as u16; // In hundredths of a second
+                encoder.write_frame(&frame)?;
+            },
+            Output::PngSequence(directory) => {
+                image.save_png(directory.join(format!("frame-{:05}.png", self.frames)))?;
+            },
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Ends the recording and returns the number of frames
+    pub fn finish(self) -> Result<u32, ImageError> {
+        if let Output::Gif(encoder) = self.output {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(self.frames)
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::sandsim::behaviors::{BehaviorSet, EXPLOSIVE_ID};
+use crate::sandsim::cell_buffer::CellWindow;
+use crate::sandsim::chunks::{ChunkEvent, ChunkMap};
+use crate::sandsim::grid::{Position, SimRng};
+use crate::sandsim::material::MaterialRegistry;
+use crate::sandsim::neighbourhood::Neighbourhood;
+use crate::sandsim::particle::*;
+use crate::sandsim::particle_action::ParticleAction;
+
+/// `Region` updates the cells of a window of the grid.
+/// The serial update uses a single region covering the whole grid, the parallel one a region per chunk.
+/// Changes to the `ChunkMap` are recorded, and applied by the grid through `apply_events`.
+pub(crate) struct Region<'a> {
+    cells: CellWindow<'a, Particle>,
+    cell_types: CellWindow<'a, ParticleId>,
+    cell_behaviors: CellWindow<'a, BehaviorSet>,
+    materials: &'a MaterialRegistry,
+    tick: u64,
+    defer_explosives: bool,
+
+    events: Vec<ChunkEvent>,
+    deferred: Vec<Position>, // Explosives left for a region covering the whole grid, see `defer_explosives`
+}
+
+impl<'a> Region<'a> {
+    pub fn new(cells: CellWindow<'a, Particle>, cell_types: CellWindow<'a, ParticleId>, cell_behaviors: CellWindow<'a, BehaviorSet>, materials: &'a MaterialRegistry, tick: u64) -> Self {
+        Self { cells, cell_types, cell_behaviors, materials, tick, defer_explosives: false, events: vec![], deferred: vec![] }
+    }
+
+    /// Leaves the particles with an `Explosive` behavior to be updated later, see `take_deferred`.
+    /// Their blasts reach further than a region of the parallel update covers.
+    #[cfg(feature = "parallel")]
+    pub fn defer_explosives(&mut self) {
+        self.defer_explosives = true;
+    }
+
+    /// Positions of the explosives left by `update_cell`, in the order they were met
+    #[cfg(feature = "parallel")]
+    pub fn take_deferred(&mut self) -> Vec<Position> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    pub fn apply_events(&mut self, chunks: &mut ChunkMap) {
+        for event in self.events.drain(..) {
+            chunks.apply(event);
+        }
+    }
+
+    pub fn update_cell(&mut self, (x, y): Position, dt: f64, rng: &mut SimRng) {
+        // Particles without behaviors, e.g. most static solids, have nothing to do
+        if self.cell_behaviors[(x, y)].is_empty() {
+            return;
+        }
+        if self.cells[(x, y)].get_last_update_tick() == self.tick {
+            return;
+        }
+        if self.defer_explosives && self.cell_behaviors[(x, y)].contains(EXPLOSIVE_ID) {
+            self.deferred.push((x, y));
+            return;
+        }
+        self.cells[(x, y)].set_last_update_tick(self.tick);
+
+        let mut neighbourhood = Neighbourhood::new(&mut self.cell_types, &mut self.cell_behaviors, self.materials);
+        let modified = self.cells[(x, y)].update((x, y), dt, &mut neighbourhood, rng);
+        let touched = neighbourhood.take_touched();
+        let awake = self.cells[(x, y)].is_awake();
+
+        // Swaps are relative to the current cell
+        let mut new_position = (x, y);
+        if modified {
+            // Handle particle actions
+            // Explosives destroyed by a blast add their own blast at the end, each particle exploding once
+            let mut actions: VecDeque<_> = self.cells[(x, y)].get_required_actions().into();
+
+            // Move first: the behaviors give the positions of their actions once the particle has moved
+            new_position = self.cells[(x, y)].get_position();
+            let moved = new_position != (x, y) && self.cells.in_bounds(new_position);
+            if moved {
+                self.swap((x, y), new_position);
+            }
+
+            let mut transform = None;
+            while let Some(action) = actions.pop_front() {
+                match action {
+                    ParticleAction::KillParticle { position } => {
+                        let empty = self.materials.create(EMPTY_ID, position, rng);
+                        self.set(position, empty);
+                    },
+                    ParticleAction::BlastParticle { position } => {
+                        if position != (x, y) && self.cell_behaviors.get(position).is_some_and(|ids| ids.contains(EXPLOSIVE_ID)) {
+                            let neighbourhood = Neighbourhood::new(&mut self.cell_types, &mut self.cell_behaviors, self.materials);
+                            actions.extend(self.cells[position].detonate(position, &neighbourhood, rng));
+                        }
+                        let empty = self.materials.create(EMPTY_ID, position, rng);
+                        self.set(position, empty);
+                    },
+                    ParticleAction::SpawnParticle { position, material } => {
+                        if self.cells.get(position).is_some_and(|particle| particle.get_id() == EMPTY_ID) {
+                            let particle = self.materials.create(material, position, rng);
+                            self.set(position, particle);
+                        }
+                    },
+                    ParticleAction::Transform { material, temperature } => {
+                        transform = Some((material, temperature));
+                    },
+                    ParticleAction::ApplyImpulse { position, velocity } => {
+                        if let Some(particle) = self.cells.get_mut(position) {
+                            particle.apply_impulse(velocity);
+                            self.events.push(ChunkEvent::Wake(position));
+                        }
+                    },
+                    _ => panic!("Action should be handled by the particle, not the grid"),
+                }
+            }
+
+            if let Some((material, temperature)) = transform {
+                let position = if moved { new_position } else { (x, y) };
+                let mut particle = self.materials.create(material, position, rng);
+                particle.set_temperature(temperature);
+                particle.set_last_update_tick(self.tick);
+                self.set(position, particle);
+            }
+
+            self.events.push(ChunkEvent::Wake((x, y)));
+            self.events.push(ChunkEvent::Draw((x, y)));
+        }
+
+        // The behaviors moved ids around in the neighbourhood, make sure they match the actual particles
+        for position in touched {
+            self.sync_cell_ids(position);
+        }
+
+        if awake {
+            self.events.push(ChunkEvent::KeepAwake(new_position));
+        }
+    }
+
+    /// Replaces the particle at the given position, if it is in the region
+    fn set(&mut self, position: Position, value: Particle) {
+        if let Some(cell) = self.cells.get_mut(position) {
+            *cell = value;
+            self.sync_cell_ids(position);
+            self.events.push(ChunkEvent::Wake(position));
+            self.events.push(ChunkEvent::Draw(position));
+        }
+    }
+
+    fn swap(&mut self, a: Position, b: Position) {
+        if self.cells[a].get_id() == EMPTY_ID && self.cells[b].get_id() == EMPTY_ID { return; }
+
+        self.cells.swap(a, b);
+        self.cell_types.swap(a, b);
+        self.cell_behaviors.swap(a, b);
+
+        // Force the update and redraw on both
+        for position in [a, b] {
+            self.events.push(ChunkEvent::Wake(position));
+            self.events.push(ChunkEvent::Draw(position));
+        }
+    }
+
+    fn sync_cell_ids(&mut self, position: Position) {
+        if let Some(particle) = self.cells.get(position) {
+            self.cell_types[position] = particle.get_id();
+            self.cell_behaviors[position] = particle.get_behaviors_ids();
+        }
+    }
+}
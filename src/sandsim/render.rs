@@ -0,0 +1,89 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::color::Color;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Encode(png::EncodingError),
+    Gif(gif::EncodingError),
+    TooLarge { width: u32, height: u32 },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(error) => write!(f, "Could not write the image: {}", error),
+            ImageError::Encode(error) => write!(f, "Could not encode the image: {}", error),
+            ImageError::Gif(error) => write!(f, "Could not encode the animation: {}", error),
+            ImageError::TooLarge { width, height } => write!(f, "The image is too large ({}x{})", width, height),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<std::io::Error> for ImageError {
+    fn from(error: std::io::Error) -> Self {
+        ImageError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(error: png::EncodingError) -> Self {
+        ImageError::Encode(error)
+    }
+}
+
+impl From<gif::EncodingError> for ImageError {
+    fn from(error: gif::EncodingError) -> Self {
+        ImageError::Gif(error)
+    }
+}
+
+/// `RgbaImage` is a picture of the grid, 8 bits per channel, row-major
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>, // 4 bytes per pixel
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, data: vec![0; (width * height * 4) as usize] }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        let index = ((y * self.width + x) * 4) as usize;
+        Color::rgba(self.data[index], self.data[index + 1], self.data[index + 2], self.data[index + 3])
+    }
+
+    /// Paints a `size` x `size` square whose top left corner is at the given pixel
+    pub fn fill_square(&mut self, x: u32, y: u32, size: u32, color: Color) {
+        let pixel = [color.r, color.g, color.b, color.a];
+        for py in y..y + size {
+            let start = ((py * self.width + x) * 4) as usize;
+            for chunk in self.data[start..start + (size * 4) as usize].chunks_exact_mut(4) {
+                chunk.copy_from_slice(&pixel);
+            }
+        }
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> Result<(), ImageError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
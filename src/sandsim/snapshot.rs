@@ -0,0 +1,182 @@
+use std::fmt;
+
+use crate::color::Color;
+
+/// First bytes of every snapshot file
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"SGSV";
+/// Version of the snapshot format written by `Grid::save`. Only this version can be loaded.
+pub const SNAPSHOT_VERSION: u16 = 5;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    NotASnapshot,
+    UnsupportedVersion { found: u16, supported: u16 },
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "Could not access the snapshot file: {}", error),
+            SnapshotError::NotASnapshot => write!(f, "The file is not a grid snapshot"),
+            SnapshotError::UnsupportedVersion { found, supported } =>
+                write!(f, "The snapshot uses version {} of the format, only version {} is supported", found, supported),
+            SnapshotError::Invalid(message) => write!(f, "Invalid snapshot: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(error: std::io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+/// `StateWriter` encodes values in little endian, for snapshots and behavior states
+#[derive(Default)]
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u128(&mut self, value: u128) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_str(&mut self, value: &str) {
+        self.write_u16(value.len() as u16);
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub fn write_color(&mut self, color: Color) {
+        self.write_bytes(&[color.r, color.g, color.b, color.a]);
+    }
+
+    /// Writes the bytes written by `write` to another writer, preceded by their length
+    pub fn write_block(&mut self, write: impl FnOnce(&mut StateWriter)) {
+        let mut block = StateWriter::new();
+        write(&mut block);
+        self.write_u32(block.bytes.len() as u32);
+        self.write_bytes(&block.bytes);
+    }
+}
+
+/// `StateReader` decodes the values written by a `StateWriter`, failing instead of panicking on truncated data
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if len > self.bytes.len() {
+            return Err(SnapshotError::Invalid("unexpected end of data".to_string()));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SnapshotError> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u128(&mut self) -> Result<u128, SnapshotError> {
+        Ok(u128::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, SnapshotError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, SnapshotError> {
+        Ok(f64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_str(&mut self) -> Result<String, SnapshotError> {
+        let len = self.read_u16()? as usize;
+        String::from_utf8(self.read_bytes(len)?.to_vec())
+            .map_err(|_| SnapshotError::Invalid("a name is not valid UTF-8".to_string()))
+    }
+
+    pub fn read_color(&mut self) -> Result<Color, SnapshotError> {
+        let [r, g, b, a] = self.read_array()?;
+        Ok(Color::rgba(r, g, b, a))
+    }
+
+    /// Reads a block written by `StateWriter::write_block`
+    pub fn read_block(&mut self) -> Result<StateReader<'a>, SnapshotError> {
+        let len = self.read_u32()? as usize;
+        Ok(StateReader::new(self.read_bytes(len)?))
+    }
+}
@@ -7,6 +7,11 @@ use sdl2::render::Canvas;
 use sdl2::video::Window;
 use sdl2::EventPump; //, Sdl}; //, VideoSubsystem};
 
+use sandgamebase::color::Color;
+use sandgamebase::sandsim::grid::Grid;
+
+pub const PIXEL_SIZE: i32 = 5;
+
 pub struct Ui {
     pub canvas: Canvas<Window>,
     // sdl_context: Sdl,
@@ -35,7 +40,7 @@ impl Ui {
             .unwrap();
         let event_pump = sdl_context.event_pump().unwrap();
         let mut fps_manager = FPSManager::new();
-        fps_manager.set_framerate(fps_target as u32).unwrap();
+        fps_manager.set_framerate(fps_target).unwrap();
         Ui {
             canvas,
             // sdl_context,
@@ -68,8 +73,20 @@ impl Ui {
         events
     }
 
+    pub fn draw_grid(&mut self, grid: &mut Grid) {
+        for ((x, y), color) in grid.take_cells_to_draw() {
+            let rect = sdl2::rect::Rect::new(x * PIXEL_SIZE, y * PIXEL_SIZE, PIXEL_SIZE as u32, PIXEL_SIZE as u32);
+            self.canvas.set_draw_color(to_sdl_color(color));
+            self.canvas.fill_rect(rect).unwrap();
+        }
+    }
+
     pub fn finish_frame(&mut self) {
         self.canvas.present();
         self.fps = 1000. / self.fps_manager.delay() as f64;
     }
-}
\ No newline at end of file
+}
+
+fn to_sdl_color(color: Color) -> sdl2::pixels::Color {
+    sdl2::pixels::Color::RGBA(color.r, color.g, color.b, color.a)
+}
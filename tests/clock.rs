@@ -0,0 +1,55 @@
+use sandgamebase::sandsim::clock::SimulationClock;
+
+// Durations are powers of two, so that the accumulated time is exact
+
+#[test]
+fn ticks_do_not_depend_on_the_frame_rate() {
+    let mut small_frames = SimulationClock::new(64., 100);
+    let ticks: u32 = (0..128).map(|_| small_frames.advance(1. / 128.)).sum();
+    assert_eq!(ticks, 64);
+
+    let mut large_frame = SimulationClock::new(64., 100);
+    assert_eq!(large_frame.advance(1.), 64);
+
+    let mut uneven_frames = SimulationClock::new(64., 100);
+    let ticks: u32 = [0.25, 0.125, 0.5, 0.0625, 0.0625].into_iter().map(|elapsed| uneven_frames.advance(elapsed)).sum();
+    assert_eq!(ticks, 64);
+}
+
+#[test]
+fn time_scale_multiplies_the_ticks() {
+    let mut clock = SimulationClock::new(64., 100);
+    clock.set_time_scale(0.5);
+    assert_eq!(clock.advance(1.), 32);
+    clock.speed_up();
+    clock.speed_up();
+    assert_eq!(clock.advance(1.), 100, "twice as fast, limited to the catch-up limit");
+}
+
+#[test]
+fn catch_up_is_limited() {
+    let mut clock = SimulationClock::new(64., 16);
+    assert_eq!(clock.advance(1.), 16);
+    // The backlog was dropped
+    assert_eq!(clock.advance(1. / 64.), 1);
+}
+
+#[test]
+fn paused_clock_only_steps() {
+    let mut clock = SimulationClock::new(64., 16);
+    clock.step();
+    assert_eq!(clock.advance(1. / 64.), 1, "steps are ignored while running");
+
+    clock.toggle_pause();
+    assert!(clock.is_paused());
+    assert_eq!(clock.advance(1.), 0);
+    clock.step();
+    clock.step();
+    assert_eq!(clock.advance(1.), 2);
+    assert_eq!(clock.advance(1.), 0);
+
+    clock.toggle_pause();
+    assert!(!clock.is_paused());
+    assert_eq!(clock.time_scale(), 1.);
+    assert_eq!(clock.advance(1. / 8.), 8);
+}
@@ -0,0 +1,31 @@
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+fn count(grid: &Grid, id: ParticleId) -> usize {
+    (0..grid.height).flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+        .filter(|&position| grid.get_particle_id(position) == id)
+        .count()
+}
+
+/// A row of acid poured on a floor of the given material, and how much of the floor is left a few seconds later
+fn pour_acid_on(floor: ParticleId) -> usize {
+    let mut grid = Grid::with_seed(16, 6, 1);
+    for x in 0..grid.width {
+        grid.spawn((x, 5), floor);
+        grid.spawn((x, 4), ACID_ID);
+    }
+    for _ in 0..300 {
+        grid.update(1. / 60.);
+    }
+    count(&grid, floor)
+}
+
+#[test]
+fn resistant_materials_survive_acid() {
+    assert_eq!(pour_acid_on(GLASS_ID), 16);
+}
+
+#[test]
+fn other_materials_dissolve_in_acid() {
+    assert!(pour_acid_on(SAND_ID) < 8, "most of the sand should have been dissolved");
+}
@@ -0,0 +1,36 @@
+use sandgamebase::sandsim::brush_settings::make_brush_settings_map;
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::material::MaterialRegistry;
+use sandgamebase::sandsim::particle::*;
+
+const TICKS: usize = 120;
+
+/// Paints a mix of materials while the grid runs, and returns the final snapshot
+fn run(seed: u64, parallel: bool) -> Vec<u8> {
+    let mut grid = Grid::with_seed(48, 48, seed);
+    #[cfg(feature = "parallel")]
+    grid.set_parallel(parallel);
+    #[cfg(not(feature = "parallel"))]
+    assert!(!parallel);
+
+    let brushes = make_brush_settings_map(&MaterialRegistry::builtin());
+    for i in 0..TICKS {
+        let id = [SAND_ID, WATER_ID, WOOD_ID, FIRE_ID, OIL_ID, GUNPOWDER_ID][i % 6];
+        grid.set_circle(((i as i32 * 7) % 48, 8 + (i as i32 % 30)), brushes.get(&id).unwrap());
+        grid.update(1. / 60.);
+    }
+    grid.to_snapshot()
+}
+
+#[test]
+fn same_seed_gives_the_same_grid() {
+    assert!(run(7, false) == run(7, false));
+    assert!(run(7, false) != run(8, false));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn same_seed_gives_the_same_grid_in_parallel() {
+    assert!(run(7, true) == run(7, true));
+    assert!(run(7, true) != run(8, true));
+}
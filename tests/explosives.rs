@@ -0,0 +1,45 @@
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+fn count(grid: &Grid, id: ParticleId) -> usize {
+    (0..grid.height).flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+        .filter(|&position| grid.get_particle_id(position) == id)
+        .count()
+}
+
+#[test]
+fn dissolved_explosives_do_not_explode() {
+    let mut grid = Grid::with_seed(32, 32, 1);
+    for x in 0..32 {
+        grid.spawn((x, 31), STONE_ID);
+    }
+    for y in 26..31 {
+        for x in 8..24 {
+            grid.spawn((x, y), GUNPOWDER_ID);
+        }
+    }
+    grid.spawn((16, 25), ACID_ID);
+
+    for _ in 0..300 {
+        grid.update(1. / 60.);
+        assert_eq!(count(&grid, FIRE_ID), 0, "the gunpowder exploded");
+    }
+    assert!(count(&grid, GUNPOWDER_ID) >= 16 * 5 - 10);
+}
+
+#[test]
+fn blasts_set_off_explosives() {
+    let mut grid = Grid::with_seed(32, 32, 1);
+    for x in 0..32 {
+        grid.spawn((x, 31), STONE_ID);
+    }
+    for x in 4..28 {
+        grid.spawn((x, 30), GUNPOWDER_ID);
+    }
+    grid.get_mut((4, 30)).set_temperature(400.);
+
+    for _ in 0..10 {
+        grid.update(1. / 60.);
+    }
+    assert_eq!(count(&grid, GUNPOWDER_ID), 0, "the blast should have spread along the gunpowder");
+}
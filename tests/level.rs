@@ -0,0 +1,67 @@
+use sandgamebase::color::Color;
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::level::*;
+use sandgamebase::sandsim::material::MaterialRegistry;
+use sandgamebase::sandsim::particle::*;
+
+/// Encodes a row-major RGBA image as a PNG file
+fn write_png(path: &std::path::Path, width: u32, height: u32, pixels: &[Color]) {
+    let mut encoder = png::Encoder::new(std::fs::File::create(path).unwrap(), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data: Vec<u8> = pixels.iter().flat_map(|color| [color.r, color.g, color.b, color.a]).collect();
+    encoder.write_header().unwrap().write_image_data(&data).unwrap();
+}
+
+#[test]
+fn off_palette_colors_get_the_closest_material() {
+    let pixels = [
+        Color::rgb(246, 215, 176), Color::rgb(240, 210, 170), // Sand, then almost sand
+        Color::rgb(35, 115, 200), Color::rgba(240, 210, 170, 20), // Almost water, then transparent
+    ];
+    let path = std::env::temp_dir().join(format!("sandgamebase-test-{}.png", std::process::id()));
+    write_png(&path, 2, 2, &pixels);
+
+    let materials = MaterialRegistry::builtin();
+    let mut grid = Grid::with_seed(3, 2, 1);
+    let result = grid.load_level(&path, &Palette::from_materials(&materials), FitMode::Crop);
+    std::fs::remove_file(&path).unwrap();
+    result.unwrap();
+
+    assert_eq!(grid.get_particle_id((0, 0)), SAND_ID);
+    assert_eq!(grid.get_particle_id((1, 0)), SAND_ID);
+    assert_eq!(grid.get_particle_id((0, 1)), WATER_ID);
+    assert_eq!(grid.get_particle_id((1, 1)), EMPTY_ID);
+    assert_eq!(grid.get_particle_id((2, 0)), EMPTY_ID, "cells outside of a cropped image should be empty");
+}
+
+#[test]
+fn palette_files_pick_the_materials() {
+    let materials = MaterialRegistry::builtin();
+    let palette = Palette::from_toml_str(r#"
+        [[entry]]
+        color = [0, 0, 0]
+        material = "stone"
+
+        [[entry]]
+        color = [255, 0, 0]
+        material = "lava"
+    "#, &materials).unwrap();
+    assert_eq!(palette.material_for(Color::rgb(20, 10, 10)), STONE_ID);
+    assert_eq!(palette.material_for(Color::rgb(200, 40, 0)), LAVA_ID);
+    assert_eq!(palette.material_for(Color::rgba(255, 0, 0, 0)), EMPTY_ID);
+
+    assert!(Palette::from_toml_str("[[entry]]\ncolor = [0, 0, 0]\nmaterial = \"unobtainium\"", &materials).is_err());
+    assert!(Palette::from_toml_str("entry = []", &materials).is_err());
+}
+
+#[test]
+fn resized_images_cover_the_grid() {
+    let image = LevelImage { width: 2, height: 1, pixels: vec![Color::rgb(246, 215, 176), Color::rgb(30, 120, 190)] };
+    let materials = MaterialRegistry::builtin();
+    let mut grid = Grid::with_seed(4, 2, 1);
+    grid.fill_from_image(&image, &Palette::from_materials(&materials), FitMode::Resize);
+    for y in 0..2 {
+        assert_eq!((0..4).map(|x| grid.get_particle_id((x, y))).collect::<Vec<_>>(), [SAND_ID, SAND_ID, WATER_ID, WATER_ID]);
+    }
+}
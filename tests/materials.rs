@@ -0,0 +1,46 @@
+use sandgamebase::sandsim::material::{MaterialError, MaterialRegistry};
+
+fn load(extra: &str) -> Result<MaterialRegistry, MaterialError> {
+    MaterialRegistry::from_toml_str(&format!(r#"
+        [[material]]
+        id = 0
+        name = "empty"
+        color = [0, 0, 0]
+        phase = "gas"
+
+        [[material]]
+        id = 1
+        name = "sand"
+        color = [200, 180, 100]
+        {}
+    "#, extra))
+}
+
+#[test]
+fn builtin_materials_are_valid() {
+    MaterialRegistry::from_toml_str(sandgamebase::sandsim::material::BUILTIN_MATERIALS).unwrap();
+}
+
+#[test]
+fn color_variance_is_checked() {
+    assert!(load("color_variance = 63").is_ok());
+    assert!(matches!(load("color_variance = 100"), Err(MaterialError::Invalid(_))));
+    assert!(matches!(load("color_variance = -5"), Err(MaterialError::Invalid(_))));
+    let animated = r#"behaviors = [{ type = "AnimatedColor", colors = [[1, 2, 3]], frequency = 1.0, color_variance = 100 }]"#;
+    assert!(matches!(load(animated), Err(MaterialError::Invalid(_))));
+}
+
+#[test]
+fn ignition_radius_is_within_the_wake_radius() {
+    let flammable = |radius: i32| format!(r#"behaviors = [{{ type = "Flammable", ignition_rate = 1.0, ignition_radius = {}, burns_into = "empty" }}]"#, radius);
+    assert!(load(&flammable(sandgamebase::sandsim::chunks::WAKE_RADIUS)).is_ok());
+    assert!(matches!(load(&flammable(sandgamebase::sandsim::chunks::WAKE_RADIUS + 1)), Err(MaterialError::Invalid(_))));
+}
+
+#[test]
+fn spawn_distance_is_positive() {
+    let limited_life = |distance: &str| format!(r#"behaviors = [{{ type = "LimitedLife", lifetime = 1.0, spawn = {{ material = "sand", probability = 1.0, distance = {} }} }}]"#, distance);
+    assert!(load(&limited_life("[0, 2]")).is_ok());
+    assert!(matches!(load(&limited_life("[-1, 2]")), Err(MaterialError::Invalid(_))));
+    assert!(matches!(load(&limited_life("[1, -2]")), Err(MaterialError::Invalid(_))));
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "parallel")]
+
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+#[test]
+fn blasts_reach_past_the_parallel_reach() {
+    let mut grid = Grid::with_seed(64, 64, 1);
+    grid.set_parallel(true);
+    for x in 0..64 {
+        grid.spawn((x, 63), STONE_ID);
+    }
+    // Out of the cells the chunk of the TNT reaches, but within twice its radius
+    grid.spawn((20, 62), SAND_ID);
+    grid.spawn((33, 62), TNT_ID);
+    grid.get_mut((33, 62)).set_temperature(300.);
+
+    for _ in 0..10 {
+        grid.update(1. / 60.);
+    }
+    assert_ne!(grid.get_particle_id((33, 62)), TNT_ID, "the TNT should have exploded");
+    assert_ne!(grid.get_particle_id((20, 62)), SAND_ID, "the sand should have been thrown away");
+}
+
+/// A particle thrown across several chunks without hitting anything, and how fast it goes after each update
+fn throw(parallel: bool) -> Vec<(f64, f64)> {
+    let mut grid = Grid::with_seed(512, 128, 1);
+    grid.set_parallel(parallel);
+    grid.spawn((4, 60), SAND_ID);
+    grid.get_mut((4, 60)).apply_impulse((400., -150.));
+
+    // Long updates, so that the particle goes further than the parallel reach in one of them
+    let mut path = vec![];
+    for _ in 0..12 {
+        grid.update(1. / 20.);
+        let position = (0..grid.height).flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+            .find(|&position| grid.get_particle_id(position) == SAND_ID)
+            .unwrap();
+        path.push(grid.get(position).get_velocity());
+    }
+    path
+}
+
+#[test]
+fn fast_particles_move_the_same_in_parallel() {
+    let serial = throw(false);
+    let parallel = throw(true);
+    for (tick, (serial, parallel)) in serial.iter().zip(&parallel).enumerate() {
+        assert_eq!(serial, parallel, "different velocities after {} updates", tick + 1);
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+use sandgamebase::sandsim::recorder::*;
+
+/// Records `ticks` updates of a grid with falling sand, and returns the number of frames
+fn record(path: &std::path::Path, ticks: u32, settings: RecorderSettings) -> u32 {
+    let mut grid = Grid::with_seed(6, 5, 1);
+    grid.spawn((2, 0), SAND_ID);
+    let mut recorder = Recorder::start(path, grid.width, grid.height, settings).unwrap();
+    for _ in 0..ticks {
+        grid.update(1. / 60.);
+        recorder.capture(&grid).unwrap();
+    }
+    recorder.finish().unwrap()
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("sandgamebase-test-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn gifs_have_a_frame_every_n_ticks() {
+    let path = temp_path("recording.gif");
+    let settings = RecorderSettings { format: RecordingFormat::Gif, every_n_ticks: 2, scale: 3, ..Default::default() };
+    assert_eq!(record(&path, 7, settings), 4);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (18, 15));
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!((frame.width, frame.height, frame.delay), (18, 15, 3));
+        frames += 1;
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(frames, 4);
+}
+
+#[test]
+fn png_sequences_have_a_file_per_frame() {
+    let path = temp_path("frames");
+    let settings = RecorderSettings { format: RecordingFormat::PngSequence, every_n_ticks: 1, scale: 2, ..Default::default() };
+    assert_eq!(record(&path, 3, settings), 3);
+
+    let mut files: Vec<_> = std::fs::read_dir(&path).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    files.sort();
+    assert_eq!(files, ["frame-00000.png", "frame-00001.png", "frame-00002.png"]);
+    let info = png::Decoder::new(std::fs::File::open(path.join("frame-00002.png")).unwrap()).read_info().unwrap().info().clone();
+    std::fs::remove_dir_all(&path).unwrap();
+    assert_eq!((info.width, info.height), (12, 10));
+}
+
+#[test]
+fn recordings_stop_at_their_max_duration() {
+    let path = temp_path("short.gif");
+    let settings = RecorderSettings {
+        every_n_ticks: 1,
+        frame_delay: Duration::from_millis(30),
+        max_duration: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    let frames = record(&path, 10, settings);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(frames, 3);
+}
@@ -0,0 +1,40 @@
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+fn grid() -> Grid {
+    let mut grid = Grid::with_seed(5, 4, 1);
+    grid.spawn((0, 3), STONE_ID);
+    grid.spawn((1, 3), SAND_ID);
+    grid.spawn((4, 0), WATER_ID);
+    grid
+}
+
+#[test]
+fn renders_match_the_cell_colors() {
+    let grid = grid();
+    for scale in [1, 3] {
+        let image = grid.render(scale);
+        assert_eq!((image.width, image.height), (5 * scale, 4 * scale));
+        assert_eq!(image.data.len() as u32, image.width * image.height * 4);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let cell = ((x / scale) as i32, (y / scale) as i32);
+                assert_eq!(image.get(x, y), grid.get(cell).get_color(), "pixel ({}, {}) at scale {}", x, y, scale);
+            }
+        }
+    }
+}
+
+#[test]
+fn screenshots_decode_to_the_render() {
+    let grid = grid();
+    let image = grid.render(2);
+    let mut bytes = vec![];
+    image.write_png(&mut bytes).unwrap();
+
+    let mut reader = png::Decoder::new(&bytes[..]).read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).unwrap();
+    assert_eq!((info.width, info.height, info.color_type), (10, 8, png::ColorType::Rgba));
+    assert_eq!(&buffer[..info.buffer_size()], &image.data[..]);
+}
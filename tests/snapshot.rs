@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::material::MaterialRegistry;
+use sandgamebase::sandsim::particle::*;
+use sandgamebase::sandsim::snapshot::{SnapshotError, StateReader, SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+
+/// A grid with falling, burning and static particles, and a falling structure
+fn busy_grid() -> Grid {
+    let mut grid = Grid::with_seed(48, 48, 5);
+    for x in 0..48 {
+        grid.spawn((x, 47), STONE_ID);
+    }
+    for x in 4..20 {
+        grid.spawn((x, 10), WOOD_ID);
+        grid.spawn((x, 40), SAND_ID);
+        grid.spawn((x + 20, 30), WATER_ID);
+    }
+    grid.spawn((12, 9), FIRE_ID);
+    grid.spawn((30, 5), SEED_ID);
+    // The wood falls about 5 cells, and is still falling
+    for _ in 0..10 {
+        grid.update(1. / 60.);
+    }
+    grid
+}
+
+#[test]
+fn snapshots_round_trip() {
+    let mut grid = busy_grid();
+    let bytes = grid.to_snapshot();
+    let mut loaded = Grid::from_snapshot(&bytes, Arc::new(MaterialRegistry::builtin())).unwrap();
+    assert!(loaded.to_snapshot() == bytes);
+
+    // The loaded grid evolves exactly like the original one
+    for _ in 0..60 {
+        grid.update(1. / 60.);
+        loaded.update(1. / 60.);
+    }
+    assert!(loaded.to_snapshot() == grid.to_snapshot());
+}
+
+#[test]
+fn snapshots_round_trip_through_files() {
+    let grid = busy_grid();
+    let path = std::env::temp_dir().join(format!("sandgamebase-test-{}.sgs", std::process::id()));
+    grid.save(&path).unwrap();
+    let loaded = Grid::load(&path, Arc::new(MaterialRegistry::builtin()));
+    std::fs::remove_file(&path).unwrap();
+    assert!(loaded.unwrap().to_snapshot() == grid.to_snapshot());
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let mut bytes = busy_grid().to_snapshot();
+    let version = SNAPSHOT_MAGIC.len();
+    bytes[version..version + 2].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+    let result = Grid::from_snapshot(&bytes, Arc::new(MaterialRegistry::builtin()));
+    assert!(matches!(result, Err(SnapshotError::UnsupportedVersion { found, .. }) if found == SNAPSHOT_VERSION + 1));
+}
+
+#[test]
+fn truncated_snapshots_are_rejected() {
+    let bytes = busy_grid().to_snapshot();
+    let materials = Arc::new(MaterialRegistry::builtin());
+    assert!(matches!(Grid::from_snapshot(&bytes[..2], materials.clone()), Err(SnapshotError::NotASnapshot)));
+    for len in [SNAPSHOT_MAGIC.len() + 2, 40, bytes.len() / 2, bytes.len() - 1] {
+        assert!(Grid::from_snapshot(&bytes[..len], materials.clone()).is_err(), "a snapshot cut at {} bytes was loaded", len);
+    }
+}
+
+/// Offset of the next update rectangles, after the header and the material and behavior tables
+fn update_rects_offset(bytes: &[u8]) -> usize {
+    let mut reader = StateReader::new(bytes);
+    reader.read_bytes(SNAPSHOT_MAGIC.len() + 2 + 2 * 4 + 2 * 8 + 32 + 8 + 16).unwrap();
+    for _ in 0..reader.read_u16().unwrap() {
+        reader.read_u8().unwrap();
+        reader.read_str().unwrap();
+    }
+    for _ in 0..reader.read_u16().unwrap() {
+        reader.read_str().unwrap();
+    }
+    reader.read_bytes(0).unwrap().as_ptr() as usize - bytes.as_ptr() as usize
+}
+
+#[test]
+fn update_rects_out_of_their_chunk_are_rejected() {
+    let bytes = busy_grid().to_snapshot();
+    let offset = update_rects_offset(&bytes);
+    let materials = Arc::new(MaterialRegistry::builtin());
+    assert!(Grid::from_snapshot(&bytes, materials.clone()).is_ok());
+
+    // The first chunk covers (0, 0) to (15, 15)
+    for rect in [[-1i32, 0, 5, 5], [0, 0, 16, 5], [5, 0, 3, 5], [0, 0, 5, 1000]] {
+        let mut corrupted = bytes.clone();
+        corrupted[offset] = 1; // Awake
+        for (i, value) in rect.iter().enumerate() {
+            corrupted[offset + 1 + 4 * i..offset + 5 + 4 * i].copy_from_slice(&value.to_le_bytes());
+        }
+        let result = Grid::from_snapshot(&corrupted, materials.clone());
+        assert!(matches!(result, Err(SnapshotError::Invalid(_))), "the update rectangle {:?} was accepted", rect);
+    }
+}
@@ -0,0 +1,50 @@
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+fn find(grid: &Grid, id: ParticleId) -> Vec<(i32, i32)> {
+    (0..grid.height).flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+        .filter(|&position| grid.get_particle_id(position) == id)
+        .collect()
+}
+
+#[test]
+fn plants_grow_without_shedding_cells() {
+    let mut grid = Grid::with_seed(64, 64, 3);
+    for x in 0..64 {
+        grid.spawn((x, 63), STONE_ID);
+    }
+    grid.spawn((32, 62), SEED_ID);
+
+    let mut plant = vec![];
+    for tick in 0..1500 {
+        if tick % 10 == 0 && grid.is_empty((31, 62)) {
+            grid.spawn((31, 62), WATER_ID);
+        }
+        grid.update(1. / 60.);
+
+        let grown = find(&grid, PLANT_ID);
+        assert!(plant.iter().all(|position| grown.contains(position)), "a plant cell moved or disappeared at tick {}", tick);
+        plant = grown;
+    }
+    assert_eq!(plant.len(), 40);
+}
+
+#[test]
+fn structures_float_on_denser_liquids() {
+    let mut grid = Grid::with_seed(32, 32, 1);
+    for y in 20..32 {
+        for x in 0..32 {
+            grid.spawn((x, y), WATER_ID);
+        }
+    }
+    for x in 5..12 {
+        grid.spawn((x, 10), WOOD_ID);
+    }
+    for _ in 0..300 {
+        grid.update(1. / 60.);
+    }
+
+    let wood = find(&grid, WOOD_ID);
+    assert_eq!(wood.len(), 7);
+    assert!(wood.iter().all(|&(_, y)| y < 20), "the wood should float, found it at {:?}", wood);
+}
@@ -0,0 +1,49 @@
+use sandgamebase::sandsim::grid::Grid;
+use sandgamebase::sandsim::particle::*;
+
+fn find(grid: &Grid, id: ParticleId) -> Vec<(i32, i32)> {
+    (0..grid.height).flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+        .filter(|&position| grid.get_particle_id(position) == id)
+        .collect()
+}
+
+#[test]
+fn falling_particle_crossing_a_threshold_is_transformed() {
+    let mut grid = Grid::with_seed(8, 200, 1);
+    grid.spawn((4, 0), WATER_ID);
+    for _ in 0..30 {
+        grid.update(1. / 60.);
+    }
+    let water = find(&grid, WATER_ID);
+    assert_eq!(water.len(), 1);
+    assert!(water[0].1 > 0 && water[0].1 < grid.height - 1, "the water should still be falling");
+
+    grid.get_mut(water[0]).set_temperature(150.);
+    grid.update(1. / 60.);
+    assert!(find(&grid, WATER_ID).is_empty());
+    assert!(!find(&grid, STEAM_ID).is_empty());
+}
+
+#[test]
+fn transitions_need_the_latent_heat() {
+    let mut grid = Grid::with_seed(8, 8, 1);
+    grid.spawn((4, 7), WATER_ID);
+    grid.get_mut((4, 7)).set_temperature(-2.);
+    grid.update(1. / 60.);
+    assert_eq!(grid.get_particle_id((4, 7)), WATER_ID, "the water should not freeze before losing its latent heat");
+
+    grid.get_mut((4, 7)).set_temperature(-20.);
+    grid.update(1. / 60.);
+    assert_eq!(grid.get_particle_id((4, 7)), ICE_ID);
+    // Then warmed a little by the air around it
+    assert!(grid.get((4, 7)).get_temperature().abs() < 2., "the ice should start at the threshold");
+}
+
+#[test]
+fn heated_ice_melts() {
+    let mut grid = Grid::with_seed(8, 8, 1);
+    grid.spawn((4, 7), ICE_ID);
+    grid.get_mut((4, 7)).set_temperature(30.);
+    grid.update(1. / 60.);
+    assert_eq!(grid.get_particle_id((4, 7)), WATER_ID);
+}